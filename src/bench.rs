@@ -0,0 +1,76 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::extract;
+use crate::parse::als::AlsData;
+
+/// Wraps the system allocator to keep track of the most memory in use at once,
+/// so `measure` can report the peak memory of loading a set
+pub struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                let grown = new_size - layout.size();
+                let allocated = ALLOCATED.fetch_add(grown, Ordering::Relaxed) + grown;
+                PEAK.fetch_max(allocated, Ordering::Relaxed);
+            } else {
+                ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Time taken and the most memory allocated on top of what already was while loading a set
+pub struct Measurement {
+    pub time: Duration,
+    pub peak_bytes: usize,
+}
+
+/// Runs `f` once, measuring it with `PeakAllocator`
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Measurement) {
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let start = Instant::now();
+    let result = f();
+    let time = start.elapsed();
+
+    let peak_bytes = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+    (result, Measurement { time, peak_bytes })
+}
+
+/// Loads a set the way palsa did before pruning, decompressing all of its *xml* at once
+pub fn load_full(als_path: &str, name: &str) -> Result<AlsData, String> {
+    let extracted = extract::extract(als_path.to_string()).map_err(|e| e.to_string())?;
+    Ok(AlsData::parse(name.to_string(), extracted.xml))
+}
+
+/// Loads a set the way palsa does now, pruning its *xml* while it is decompressed
+pub fn load_pruned(als_path: &str, name: &str) -> Result<AlsData, String> {
+    let extracted = extract::extract_pruned(als_path).map_err(|e| e.to_string())?;
+    Ok(AlsData::parse(name.to_string(), extracted.xml))
+}
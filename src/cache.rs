@@ -1,11 +1,39 @@
 use crate::parse::als::{AlsData, Project};
+use crate::render;
+use image::ImageFormat;
 use serde_yaml::{from_str, to_string, to_value};
 use std::{
     fs::{self, File},
     io::{BufWriter, Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
+/// Size of the overview images shown in the als file list
+pub const THUMBNAIL_SIZE: [u32; 2] = [96, 24];
+
+/// Bumped whenever `AlsData` or the types within it change, or what is parsed into them, as
+/// sets cached before would load with defaults in place of new fields or with outdated values
+pub const CACHE_VERSION: u32 = 4;
+
+const VERSION_PATH: &str = "cache/version";
+
+/// Clears the cache when it was written with another `CACHE_VERSION`, or before the version
+/// was stored, so every set is parsed again rather than loaded with outdated fields
+pub fn clear_outdated() -> std::io::Result<()> {
+    let version = fs::read_to_string(VERSION_PATH)
+        .ok()
+        .and_then(|version| version.trim().parse::<u32>().ok());
+    if version == Some(CACHE_VERSION) {
+        return Ok(());
+    }
+
+    if Path::new("cache/").is_dir() {
+        fs::remove_dir_all("cache/")?;
+    }
+    fs::create_dir_all("cache/")?;
+    fs::write(VERSION_PATH, CACHE_VERSION.to_string())
+}
+
 /// Creates *yaml* files for faster loading as files will not have to be parsed again
 pub fn cache(projects: Vec<Project>) -> std::io::Result<()> {
     // Creates the folder for cache if it does not already exist
@@ -13,71 +41,122 @@ pub fn cache(projects: Vec<Project>) -> std::io::Result<()> {
 
     for project in projects {
         if let Some(als_data) = project.als_data {
-            for als_data in als_data {
-                let file_path = format!("cache/{}/{}.yaml", project.name, als_data.name);
-                fs::create_dir_all(&format!("cache/{}", project.name))?;
-
-                if !Path::new(&file_path).is_file() {
-                    let yaml_data =
-                        to_value(&als_data).expect("Failed to convert AlsData to YAML value!");
-                    let yaml_string =
-                        to_string(&yaml_data).expect("Failed to convert YAML value to string!");
-
-                    let file = File::create(&file_path)?;
-                    let mut buf_writer = BufWriter::new(file);
-                    buf_writer.write_all(yaml_string.as_bytes())?;
-                }
-            }
+            cache_dir(&format!("cache/{}", project.name), als_data)?;
+        }
+        if !project.backups.is_empty() {
+            cache_dir(&format!("cache/{}/Backup", project.name), project.backups)?;
         }
     }
     Ok(())
 }
 
-/// Used to retrieve `Project` when `cache` created *yaml* cache files
-pub fn retrieve() -> std::io::Result<Vec<Project>> {
-    let mut projects = Vec::new();
-
-    for entry in fs::read_dir("cache/")? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            let project_name = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Invalid project directory name",
-                    )
-                })?
-                .to_string();
-
-            let mut all_als_data = Vec::new();
-
-            for als_entry in fs::read_dir(&path)? {
-                let als_entry = als_entry?;
-                let als_path = als_entry.path();
-                let is_yaml =
-                    als_path.is_file() && als_path.extension().unwrap_or_default() == "yaml";
-
-                if is_yaml {
-                    let mut file = File::open(&als_path)?;
-                    let mut file_contents = String::new();
-                    file.read_to_string(&mut file_contents)?;
-
-                    let als_data: AlsData = from_str(&file_contents)
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-                    all_als_data.push(als_data);
-                }
+/// Writes the cache files of freshly parsed *als* files, replacing any outdated ones
+fn cache_dir(dir: &str, all_als_data: Vec<AlsData>) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    for als_data in all_als_data {
+        let file_path = format!("{}/{}.yaml", dir, als_data.name);
+
+        let yaml_data = to_value(&als_data).expect("Failed to convert AlsData to YAML value!");
+        let yaml_string = to_string(&yaml_data).expect("Failed to convert YAML value to string!");
+
+        let file = File::create(&file_path)?;
+        let mut buf_writer = BufWriter::new(file);
+        buf_writer.write_all(yaml_string.as_bytes())?;
+
+        create_thumbnail(&thumbnail_path(dir, &als_data.name), &als_data)?;
+    }
+    Ok(())
+}
+
+/// Whether an *als* file has a cache file which is newer than it,
+/// sets saved since they were cached have to be parsed again
+pub fn is_cached(cache_dir: &str, name: &str, als_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    match (
+        modified(Path::new(&format!("{}/{}.yaml", cache_dir, name))),
+        modified(als_path),
+    ) {
+        (Ok(cached), Ok(saved)) => cached >= saved,
+        (Ok(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+/// Deletes the cache files of a set which no longer exists
+pub fn remove(cache_dir: &str, name: &str) -> std::io::Result<()> {
+    for path in [
+        PathBuf::from(format!("{}/{}.yaml", cache_dir, name)),
+        thumbnail_path(cache_dir, name),
+    ] {
+        if path.is_file() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Where the overview image of a set shown in the als file list is cached
+pub fn thumbnail_path(cache_dir: &str, name: &str) -> PathBuf {
+    PathBuf::from(format!("{}/{}.png", cache_dir, name))
+}
+
+fn create_thumbnail(path: &Path, als_data: &AlsData) -> std::io::Result<()> {
+    render::thumbnail(als_data, THUMBNAIL_SIZE)
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(std::io::Error::other)
+}
+
+/// Used to retrieve a `Project` once `cache` created its *yaml* cache files
+pub fn retrieve_project(name: &str) -> std::io::Result<Project> {
+    let path = Path::new("cache/").join(name);
+
+    let backup_path = path.join("Backup");
+    let backups = if backup_path.is_dir() {
+        retrieve_dir(&backup_path)?
+    } else {
+        Vec::new()
+    };
+
+    let als_data = if path.is_dir() {
+        retrieve_dir(&path)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(Project {
+        name: name.to_string(),
+        als_data: Some(als_data),
+        backups,
+    })
+}
+
+fn retrieve_dir(path: &Path) -> std::io::Result<Vec<AlsData>> {
+    let mut all_als_data = Vec::new();
+
+    for als_entry in fs::read_dir(path)? {
+        let als_entry = als_entry?;
+        let als_path = als_entry.path();
+        let is_yaml = als_path.is_file() && als_path.extension().unwrap_or_default() == "yaml";
+
+        if is_yaml {
+            let mut file = File::open(&als_path)?;
+            let mut file_contents = String::new();
+            file.read_to_string(&mut file_contents)?;
+
+            let als_data: AlsData = from_str(&file_contents)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+            // Caches from before thumbnails existed are missing them
+            let thumbnail_path = als_path.with_extension("png");
+            if !thumbnail_path.is_file() {
+                create_thumbnail(&thumbnail_path, &als_data)?;
             }
 
-            projects.push(Project {
-                name: project_name,
-                als_data: Some(all_als_data),
-            });
+            all_als_data.push(als_data);
         }
     }
 
-    Ok(projects)
+    Ok(all_als_data)
 }
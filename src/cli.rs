@@ -0,0 +1,497 @@
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "bench")]
+use crate::bench;
+use crate::diff;
+use crate::edit::{self, Edit, EditableSet, TrackRef};
+use crate::export::{
+    self, archive,
+    json::{self, JsonExport},
+    midi,
+};
+use crate::palsa;
+use crate::parallel;
+use crate::parse::als::{AlsData, Project};
+use crate::progress::Progress;
+use crate::render::{Overview, RenderOptions};
+use crate::rules::{self, Rules};
+use crate::samples;
+use crate::trash;
+
+const USAGE: &str = "Usage:
+    palsa                                  Opens the gui
+    palsa archive <project> [-o file]      Archives a project as a zip along with the samples
+                                           its sets use from elsewhere, to exports/ by default
+    palsa bench <als>...                   Compares the time and peak memory of loading als
+                                           files with and without pruning their xml, only
+                                           when built with the bench feature
+    palsa diff <old> <new>                 Lists the changes between two als files
+    palsa edit <als> [edits] [-o file]     Writes a copy of an als file with edits made to it,
+                                           next to it as \"<name> (edited).als\" by default
+        --rename-track <track>=<name>      Renames a track, tracks are given by their name
+                                           or their id such as #12
+        --track-color <track>=<color>      Changes the colour index of a track
+        --clip-color <track>:<clip>=<color>
+                                           Changes the colour index of a track's nth clip
+        --tempo <bpm>                      Changes the tempo
+        --delete-track <track>             Deletes a midi or audio track
+    palsa export --json [path] [-o file]   Exports an als file, a project directory or,
+                                           without a path, every project as json
+    palsa export --midi <als> [-o file]    Exports the arrangement midi clips of an als file
+                                           as a standard midi file, to exports/ by default
+    palsa render <als> [-o file]           Draws the arrangement overview of an als file
+                                           as an svg or png, to exports/ by default
+    palsa rules <rules> [path] [--apply]   Lists how the renaming and recolouring rules in a
+                                           yaml file would change the tracks of an als file,
+                                           a project or every project, --apply saves the
+                                           changed sets next to the originals
+    palsa unused <project> [--trash]       Lists the files in a project's Samples directory
+                                           which none of its sets or backups use, --trash
+                                           moves them to the trash
+    palsa schema                           Prints the json schema of exported json";
+
+/// Runs a command given on the command line instead of opening the gui
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args[0].as_str() {
+        "archive" => archive(&args[1..]),
+        "bench" => bench(&args[1..]),
+        "diff" => diff(&args[1..]),
+        "edit" => edit(&args[1..]),
+        "export" => export(&args[1..]),
+        "render" => render(&args[1..]),
+        "rules" => apply_rules(&args[1..]),
+        "unused" => unused(&args[1..]),
+        "schema" => {
+            println!("{}", json::schema_json());
+            Ok(())
+        }
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        command => Err(format!("Unknown command \"{}\"\n\n{}", command, USAGE)),
+    }
+}
+
+fn archive(args: &[String]) -> Result<(), String> {
+    let (args, output) = take_option(args, &["-o", "--output"])?;
+    let [project_dir] = args.as_slice() else {
+        return Err(format!(
+            "Expected a project directory to archive\n\n{}",
+            USAGE
+        ));
+    };
+
+    let project_dir = Path::new(project_dir);
+    if !project_dir.is_dir() {
+        return Err(format!(
+            "{} is not a project directory",
+            project_dir.display()
+        ));
+    }
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let name = project_dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            export::export_path(&name, "zip").map_err(|e| e.to_string())?
+        }
+    };
+
+    let manifest = archive::archive(project_dir, &output)?;
+    println!(
+        "Archived {} sets with {} collected samples to {}",
+        manifest.sets.len(),
+        manifest.collected.len(),
+        output.display()
+    );
+    if !manifest.missing.is_empty() {
+        eprintln!(
+            "Warning: {} samples could not be found, they are listed in manifest.json",
+            manifest.missing.len()
+        );
+    }
+    Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), String> {
+    let [old_path, new_path] = args else {
+        return Err(format!("Expected two als files to compare\n\n{}", USAGE));
+    };
+
+    let old = parse_set(Path::new(old_path))?;
+    let new = parse_set(Path::new(new_path))?;
+
+    print!("{}", diff::diff(&old, &new));
+    Ok(())
+}
+
+fn edit(args: &[String]) -> Result<(), String> {
+    let (mut args, output) = take_option(args, &["-o", "--output"])?;
+
+    let mut edits = Vec::new();
+    let options = [
+        "--rename-track",
+        "--track-color",
+        "--clip-color",
+        "--tempo",
+        "--delete-track",
+    ];
+    for option in options {
+        while let (rest, Some(value)) = take_option(&args, &[option])? {
+            args = rest;
+            edits.push(parse_edit(option, &value)?);
+        }
+    }
+
+    let [als_path] = args.as_slice() else {
+        return Err(format!("Expected an als file to edit\n\n{}", USAGE));
+    };
+    if edits.is_empty() {
+        return Err(format!("Expected edits to make\n\n{}", USAGE));
+    }
+
+    let als_path = Path::new(als_path);
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => edit::edited_path(als_path),
+    };
+
+    let mut set = EditableSet::open(als_path)?;
+    set.apply(&edits)?;
+    set.save(&output)?;
+    println!("Saved {}", output.display());
+    Ok(())
+}
+
+/// Parses the value of one of `edit`'s options
+fn parse_edit(option: &str, value: &str) -> Result<Edit, String> {
+    let invalid = || format!("Invalid value \"{}\" for {}", value, option);
+    let color = |color: &str| color.parse::<usize>().map_err(|_| invalid());
+
+    match option {
+        "--rename-track" => {
+            let (track, name) = value.split_once('=').ok_or_else(invalid)?;
+            Ok(Edit::RenameTrack {
+                track: track.parse()?,
+                name: name.to_string(),
+            })
+        }
+        "--track-color" => {
+            let (track, index) = value.split_once('=').ok_or_else(invalid)?;
+            Ok(Edit::RecolourTrack {
+                track: track.parse()?,
+                color: color(index)?,
+            })
+        }
+        "--clip-color" => {
+            let (clip, index) = value.rsplit_once('=').ok_or_else(invalid)?;
+            let (track, clip) = clip.rsplit_once(':').ok_or_else(invalid)?;
+            Ok(Edit::RecolourClip {
+                track: track.parse()?,
+                clip: clip.parse().map_err(|_| invalid())?,
+                color: color(index)?,
+            })
+        }
+        "--tempo" => Ok(Edit::SetTempo(value.parse().map_err(|_| invalid())?)),
+        _ => Ok(Edit::DeleteTrack(value.parse::<TrackRef>()?)),
+    }
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let (args, output) = take_option(args, &["-o", "--output"])?;
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--json") => {
+            let projects = load_projects(args.get(1).map(Path::new))?;
+            write_output(output.as_deref(), &JsonExport::new(projects).to_json()?)
+        }
+        Some("--midi") => {
+            let Some(als_path) = args.get(1).map(Path::new) else {
+                return Err(format!("Expected an als file to export\n\n{}", USAGE));
+            };
+            let output = match output {
+                Some(output) => PathBuf::from(output),
+                None => {
+                    let name = als_path.file_stem().unwrap_or_default().to_string_lossy();
+                    export::export_path(&name, "mid").map_err(|e| e.to_string())?
+                }
+            };
+
+            let (bytes, salvaged) = midi::export_midi(als_path)?;
+            fs::write(&output, bytes)
+                .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            println!("Exported {}", output.display());
+            if salvaged {
+                warn_salvaged(&als_path.display().to_string());
+            }
+            Ok(())
+        }
+        _ => Err(format!("Expected an export format\n\n{}", USAGE)),
+    }
+}
+
+fn render(args: &[String]) -> Result<(), String> {
+    let (args, output) = take_option(args, &["-o", "--output"])?;
+    let [als_path] = args.as_slice() else {
+        return Err(format!("Expected an als file to render\n\n{}", USAGE));
+    };
+
+    let als_data = parse_set(Path::new(als_path))?;
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => export::export_path(&als_data.name, "png").map_err(|e| e.to_string())?,
+    };
+
+    Overview::new(&als_data, RenderOptions::default()).save(&output)?;
+    println!("Rendered {}", output.display());
+    Ok(())
+}
+
+fn apply_rules(args: &[String]) -> Result<(), String> {
+    let apply = args.iter().any(|arg| arg == "--apply");
+    let args: Vec<&String> = args.iter().filter(|arg| *arg != "--apply").collect();
+    let (rules_path, path) = match args.as_slice() {
+        [rules_path] => (rules_path, Path::new("projects/")),
+        [rules_path, path] => (rules_path, Path::new(path.as_str())),
+        _ => return Err(format!("Expected a rules file\n\n{}", USAGE)),
+    };
+
+    let rules = Rules::load(Path::new(rules_path))?;
+    let files = rules::find_sets(path)?;
+
+    let mut errors = Vec::new();
+    let mut changed = 0;
+    for plan in rules::plan_sets(&rules, &files) {
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        println!("{}", plan.path.display());
+        for change in &plan.changes {
+            println!("    {}", change);
+        }
+        if apply {
+            match plan.apply() {
+                Ok(output) => println!("    Saved {}", output.display()),
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            }
+        }
+        changed += 1;
+    }
+
+    match (apply, changed) {
+        (_, 0) if errors.is_empty() => println!("No set out of {} needs changing", files.len()),
+        (true, _) => println!("Saved {} of {} sets", changed, files.len()),
+        (false, _) => println!(
+            "{} of {} sets would be changed, run again with --apply to save them",
+            changed,
+            files.len()
+        ),
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    Ok(())
+}
+
+fn unused(args: &[String]) -> Result<(), String> {
+    let trash = args.iter().any(|arg| arg == "--trash");
+    let args: Vec<&String> = args.iter().filter(|arg| *arg != "--trash").collect();
+    let [project_dir] = args.as_slice() else {
+        return Err(format!("Expected a project directory\n\n{}", USAGE));
+    };
+
+    let project_dir = Path::new(project_dir.as_str());
+    if !project_dir.is_dir() {
+        return Err(format!(
+            "{} is not a project directory",
+            project_dir.display()
+        ));
+    }
+
+    let unused = samples::unused_files(project_dir)?;
+    let megabytes = |bytes: u64| bytes as f64 / 1_000_000.;
+    let mut errors = Vec::new();
+    let mut freed = 0;
+    for file in &unused {
+        let relative = file.path.strip_prefix(project_dir).unwrap_or(&file.path);
+        println!("{:>10.1} MB  {}", megabytes(file.size), relative.display());
+        if trash {
+            match trash::move_to_trash(&file.path) {
+                Ok(()) => freed += file.size,
+                Err(e) => errors.push(format!("Failed to trash {}: {}", relative.display(), e)),
+            }
+        }
+    }
+
+    let total: u64 = unused.iter().map(|file| file.size).sum();
+    if trash {
+        println!(
+            "Moved {:.1} MB of {} unused files to the trash",
+            megabytes(freed),
+            unused.len()
+        );
+    } else {
+        println!(
+            "{} unused files, {:.1} MB could be freed by running again with --trash",
+            unused.len(),
+            megabytes(total)
+        );
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "bench"))]
+fn bench(_args: &[String]) -> Result<(), String> {
+    Err("Benchmarking needs palsa to be built with `--features bench`".to_string())
+}
+
+#[cfg(feature = "bench")]
+fn bench(args: &[String]) -> Result<(), String> {
+    if args.is_empty() {
+        return Err(format!("Expected als files to benchmark\n\n{}", USAGE));
+    }
+
+    let megabytes = |bytes: usize| bytes as f64 / 1_000_000.;
+    println!(
+        "{:<40} {:>12} {:>12} {:>12} {:>12}  same result",
+        "set", "full time", "full peak", "pruned time", "pruned peak"
+    );
+    for als_path in args {
+        let name = Path::new(als_path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+
+        let (full, full_measurement) = bench::measure(|| bench::load_full(als_path, &name));
+        let (pruned, pruned_measurement) = bench::measure(|| bench::load_pruned(als_path, &name));
+
+        // Both are serialized to compare them as `AlsData` is not `PartialEq`
+        let same = serde_yaml::to_string(&full?).map_err(|e| e.to_string())?
+            == serde_yaml::to_string(&pruned?).map_err(|e| e.to_string())?;
+
+        println!(
+            "{:<40} {:>9} ms {:>9.1} MB {:>9} ms {:>9.1} MB  {}",
+            name,
+            full_measurement.time.as_millis(),
+            megabytes(full_measurement.peak_bytes),
+            pruned_measurement.time.as_millis(),
+            megabytes(pruned_measurement.peak_bytes),
+            if same { "yes" } else { "no" }
+        );
+    }
+    Ok(())
+}
+
+/// Loads a single *als* file, a project directory or every project in `projects/`,
+/// warning about each damaged set
+fn load_projects(path: Option<&Path>) -> Result<Vec<Project>, String> {
+    let projects = match path {
+        Some(path) if path.is_file() => {
+            let project_name = path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            vec![Project {
+                name: project_name,
+                als_data: Some(vec![palsa::parse_als_file(path)?]),
+                backups: Vec::new(),
+            }]
+        }
+        Some(path) if path.is_dir() => {
+            vec![parallel::parallel_parse_project(path, &progress_line())?]
+        }
+        Some(path) => return Err(format!("{} does not exist", path.display())),
+        None => palsa::run_palsa(Path::new("projects/"), &progress_line())?,
+    };
+
+    for als_data in projects
+        .iter()
+        .flat_map(|project| project.als_data.iter().flatten().chain(&project.backups))
+        .filter(|als_data| als_data.salvaged)
+    {
+        warn_salvaged(&als_data.path);
+    }
+    Ok(projects)
+}
+
+/// Parses a single *als* file, warning when it is damaged
+fn parse_set(path: &Path) -> Result<AlsData, String> {
+    let als_data = palsa::parse_als_file(path)?;
+    if als_data.salvaged {
+        warn_salvaged(&als_data.path);
+    }
+    Ok(als_data)
+}
+
+fn warn_salvaged(path: &str) {
+    eprintln!(
+        "Warning: {} is damaged, only the part of it which could be salvaged was read",
+        path
+    );
+}
+
+/// Keeps rewriting a single line on stderr with the loading progress,
+/// nothing is printed when stderr is not a terminal so piped output stays clean
+fn progress_line() -> Progress {
+    if !io::stderr().is_terminal() {
+        return Progress::silent();
+    }
+
+    Progress::new(|report| {
+        if report.discovered == 0 {
+            return;
+        }
+        // Clears the rest of the line as the current set's name changes length
+        eprint!("\r{}\x1b[K", report);
+        if report.is_finished() {
+            eprintln!();
+        }
+    })
+}
+
+/// Removes an option and its value from the arguments
+fn take_option(args: &[String], names: &[&str]) -> Result<(Vec<String>, Option<String>), String> {
+    let mut args = args.to_vec();
+    match args.iter().position(|arg| names.contains(&arg.as_str())) {
+        Some(position) if position + 1 < args.len() => {
+            let value = args.remove(position + 1);
+            args.remove(position);
+            Ok((args, Some(value)))
+        }
+        Some(_) => Err(format!("Expected a value after {}", names[0])),
+        None => Ok((args, None)),
+    }
+}
+
+/// Writes to the output file when one is given and otherwise to stdout
+fn write_output(output: Option<&str>, contents: &str) -> Result<(), String> {
+    match output {
+        Some(output) => {
+            fs::write(output, contents).map_err(|e| format!("Failed to write {}: {}", output, e))
+        }
+        None => {
+            println!("{}", contents);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::io;
+
+/// How many colours are in ableton's palette, colour indexes are below it
+pub const PALETTE_SIZE: usize = 70;
+
+/// One hex colour per line, built into palsa so the palette is found wherever it is run from
+const PALETTE_FILE: &str = include_str!("../assets/palsa/default-colors.txt");
+
+fn load_colors() -> Result<[String; 70], io::Error> {
+    let mut colors = init_colors();
+
+    for (i, line) in PALETTE_FILE.lines().enumerate() {
+        if i >= 70 {
+            break; // Stop reading if more than 70 lines are encountered
+        }
+        colors[i] = line.trim().to_string();
+    }
+
+    // Check if we have exactly 70 colors
+    if colors.iter().any(|s| s.is_empty()) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The palette has fewer than 70 colours",
+        ))
+    } else {
+        Ok(colors)
+    }
+}
+
+fn init_colors() -> [String; 70] {
+    // Initalizes an array of 70 purple hex values (Unloaded texture values)
+    // Cant use [String::from("800080"); 70] so im using this bellow to avoid the copy trait :(
+    std::array::from_fn(|_| String::from("800080"))
+}
+
+/// Ableton's palette of 70 track and clip colors, purple if they could not be loaded
+pub fn palette() -> [String; 70] {
+    load_colors().unwrap_or_else(|_| init_colors())
+}
+
+/// Converts a palette hex color such as `#E594A6` to rgb
+pub fn hex_to_rgb(hex: &str) -> [u8; 3] {
+    let hex = hex.trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+            .unwrap_or(0)
+    };
+    [channel(0), channel(2), channel(4)]
+}
+
+/// Fails for colour indexes outside of the palette, which live and palsa can not show
+pub fn check_index(color: usize) -> Result<(), String> {
+    if color < PALETTE_SIZE {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid colour {}, colours go from 0 to {}",
+            color,
+            PALETTE_SIZE - 1
+        ))
+    }
+}
@@ -0,0 +1,622 @@
+use std::fmt;
+
+use crate::parse::{als::AlsData, clip::Clip, track::Track};
+
+/// Indexes of a clip as `(track, clip)` within its `AlsData`
+pub type ClipIndex = (usize, usize);
+
+/// A single difference between two versions of a set,
+/// indexes starting with `old` point into the old set and `new` into the new set
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    TempoChanged { old: f32, new: f32 },
+    TimeSignatureChanged,
+    TrackAdded { new: usize },
+    TrackRemoved { old: usize },
+    TrackRenamed { old: usize, new: usize },
+    TrackMoved { old: usize, new: usize },
+    ClipAdded { new: ClipIndex },
+    ClipRemoved { old: ClipIndex },
+    ClipRenamed { old: ClipIndex, new: ClipIndex },
+    ClipMoved { old: ClipIndex, new: ClipIndex },
+    ClipResized { old: ClipIndex, new: ClipIndex },
+    DeviceAdded { new_track: usize, device: String },
+    DeviceRemoved { old_track: usize, device: String },
+}
+
+/// All the changes needed to get from `old` to `new`
+pub struct AlsDiff<'a> {
+    pub old: &'a AlsData,
+    pub new: &'a AlsData,
+    pub changes: Vec<Change>,
+}
+
+/// Compares two parsed versions of a set, matching tracks by their id and then by name,
+/// and clips by their name or sample along with their position
+pub fn diff<'a>(old: &'a AlsData, new: &'a AlsData) -> AlsDiff<'a> {
+    let mut changes = Vec::new();
+
+    if old.tempo != new.tempo {
+        changes.push(Change::TempoChanged {
+            old: old.tempo,
+            new: new.tempo,
+        });
+    }
+    if old.time_signature != new.time_signature {
+        changes.push(Change::TimeSignatureChanged);
+    }
+
+    let track_pairs = match_tracks(&old.tracks, &new.tracks);
+
+    for (new_index, _) in new.tracks.iter().enumerate() {
+        if !track_pairs.iter().any(|&(_, n)| n == new_index) {
+            changes.push(Change::TrackAdded { new: new_index });
+        }
+    }
+    for (old_index, _) in old.tracks.iter().enumerate() {
+        if !track_pairs.iter().any(|&(o, _)| o == old_index) {
+            changes.push(Change::TrackRemoved { old: old_index });
+        }
+    }
+
+    for &(old_index, new_index) in &track_pairs {
+        if old.tracks[old_index].name != new.tracks[new_index].name {
+            changes.push(Change::TrackRenamed {
+                old: old_index,
+                new: new_index,
+            });
+        }
+    }
+    for (old_index, new_index) in moved_tracks(&track_pairs) {
+        changes.push(Change::TrackMoved {
+            old: old_index,
+            new: new_index,
+        });
+    }
+
+    for &(old_index, new_index) in &track_pairs {
+        diff_clips(
+            &mut changes,
+            (old_index, &old.tracks[old_index]),
+            (new_index, &new.tracks[new_index]),
+        );
+        diff_devices(
+            &mut changes,
+            (old_index, &old.tracks[old_index]),
+            (new_index, &new.tracks[new_index]),
+        );
+    }
+
+    AlsDiff { old, new, changes }
+}
+
+impl AlsDiff<'_> {
+    /// Clips of the old set which were changed or removed
+    pub fn changed_old_clips(&self) -> Vec<ClipIndex> {
+        self.changes
+            .iter()
+            .filter_map(|change| match *change {
+                Change::ClipRemoved { old }
+                | Change::ClipRenamed { old, .. }
+                | Change::ClipMoved { old, .. }
+                | Change::ClipResized { old, .. } => Some(old),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Clips of the new set which were changed or added
+    pub fn changed_new_clips(&self) -> Vec<ClipIndex> {
+        self.changes
+            .iter()
+            .filter_map(|change| match *change {
+                Change::ClipAdded { new }
+                | Change::ClipRenamed { new, .. }
+                | Change::ClipMoved { new, .. }
+                | Change::ClipResized { new, .. } => Some(new),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Tracks of the old set which were removed or renamed
+    pub fn changed_old_tracks(&self) -> Vec<usize> {
+        self.changes
+            .iter()
+            .filter_map(|change| match *change {
+                Change::TrackRemoved { old } | Change::TrackRenamed { old, .. } => Some(old),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Tracks of the new set which were added or renamed
+    pub fn changed_new_tracks(&self) -> Vec<usize> {
+        self.changes
+            .iter()
+            .filter_map(|change| match *change {
+                Change::TrackAdded { new } | Change::TrackRenamed { new, .. } => Some(new),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A human readable description of a change
+    pub fn describe(&self, change: &Change) -> String {
+        let old_track = |index: usize| &self.old.tracks[index];
+        let new_track = |index: usize| &self.new.tracks[index];
+        let old_clip = |(track, clip): ClipIndex| &self.old.tracks[track].clips[clip];
+        let new_clip = |(track, clip): ClipIndex| &self.new.tracks[track].clips[clip];
+        let old_position = |time: f32| self.old.time_signature.format_position(time);
+        let new_position = |time: f32| self.new.time_signature.format_position(time);
+
+        match change {
+            Change::TempoChanged { old, new } => format!("Tempo changed from {} to {}", old, new),
+            Change::TimeSignatureChanged => format!(
+                "Time signature changed from {}/{} to {}/{}",
+                self.old.time_signature.numerator,
+                self.old.time_signature.denominator,
+                self.new.time_signature.numerator,
+                self.new.time_signature.denominator
+            ),
+            Change::TrackAdded { new } => format!("Added track \"{}\"", new_track(*new).name),
+            Change::TrackRemoved { old } => {
+                format!("Removed track \"{}\"", old_track(*old).name)
+            }
+            Change::TrackRenamed { old, new } => format!(
+                "Renamed track \"{}\" to \"{}\"",
+                old_track(*old).name,
+                new_track(*new).name
+            ),
+            Change::TrackMoved { old, new } => format!(
+                "Moved track \"{}\" from position {} to {}",
+                new_track(*new).name,
+                old + 1,
+                new + 1
+            ),
+            Change::ClipAdded { new } => format!(
+                "{}: added clip {} at {}",
+                new_track(new.0).name,
+                clip_name(new_clip(*new)),
+                new_position(new_clip(*new).start)
+            ),
+            Change::ClipRemoved { old } => format!(
+                "{}: removed clip {} at {}",
+                old_track(old.0).name,
+                clip_name(old_clip(*old)),
+                old_position(old_clip(*old).start)
+            ),
+            Change::ClipRenamed { old, new } => format!(
+                "{}: renamed clip {} to {} at {}",
+                new_track(new.0).name,
+                clip_name(old_clip(*old)),
+                clip_name(new_clip(*new)),
+                new_position(new_clip(*new).start)
+            ),
+            Change::ClipMoved { old, new } => format!(
+                "{}: moved clip {} from {} to {}",
+                new_track(new.0).name,
+                clip_name(new_clip(*new)),
+                old_position(old_clip(*old).start),
+                new_position(new_clip(*new).start)
+            ),
+            Change::ClipResized { old, new } => format!(
+                "{}: resized clip {} at {} from {} to {}",
+                new_track(new.0).name,
+                clip_name(new_clip(*new)),
+                new_position(new_clip(*new).start),
+                self.old
+                    .time_signature
+                    .format_length(clip_length(old_clip(*old))),
+                self.new
+                    .time_signature
+                    .format_length(clip_length(new_clip(*new)))
+            ),
+            Change::DeviceAdded {
+                new_track: track,
+                device,
+            } => {
+                format!("{}: added device \"{}\"", new_track(*track).name, device)
+            }
+            Change::DeviceRemoved {
+                old_track: track,
+                device,
+            } => {
+                format!("{}: removed device \"{}\"", old_track(*track).name, device)
+            }
+        }
+    }
+}
+
+impl fmt::Display for AlsDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(
+                f,
+                "No changes between {} and {}",
+                self.old.name, self.new.name
+            );
+        }
+
+        writeln!(
+            f,
+            "{} changes from {} to {}:",
+            self.changes.len(),
+            self.old.name,
+            self.new.name
+        )?;
+        for change in &self.changes {
+            writeln!(f, "  {}", self.describe(change))?;
+        }
+        Ok(())
+    }
+}
+
+/// Pairs up tracks as `(old, new)` first by their id and then by name. Ids are only unique
+/// within a set, so unrelated sets share them and an id is only trusted for related tracks
+fn match_tracks(old: &[Track], new: &[Track]) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+
+    let mut match_by = |same: &dyn Fn(&Track, &Track) -> bool| {
+        for (old_index, old_track) in old.iter().enumerate() {
+            if pairs.iter().any(|&(o, _)| o == old_index) {
+                continue;
+            }
+            let new_index = new.iter().enumerate().position(|(new_index, new_track)| {
+                !pairs.iter().any(|&(_, n)| n == new_index) && same(old_track, new_track)
+            });
+            if let Some(new_index) = new_index {
+                pairs.push((old_index, new_index));
+            }
+        }
+    };
+
+    match_by(&|a, b| a.id >= 0 && a.id == b.id && related(a, b));
+    match_by(&|a, b| a.name == b.name);
+
+    pairs.sort();
+    pairs
+}
+
+/// Finds the fewest tracks which have to be moved to get from the old order to the new one,
+/// which are the tracks outside of the longest run that kept their relative order
+fn moved_tracks(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // `pairs` is sorted by the old index so only the new indexes need to be increasing
+    let mut lengths = vec![1; pairs.len()];
+    let mut previous = vec![None; pairs.len()];
+    for i in 0..pairs.len() {
+        for j in 0..i {
+            if pairs[j].1 < pairs[i].1 && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut in_order = vec![false; pairs.len()];
+    let mut current = (0..pairs.len()).max_by_key(|&i| lengths[i]);
+    while let Some(i) = current {
+        in_order[i] = true;
+        current = previous[i];
+    }
+
+    pairs
+        .iter()
+        .zip(in_order)
+        .filter(|(_, in_order)| !in_order)
+        .map(|(&pair, _)| pair)
+        .collect()
+}
+
+/// Whether two tracks with the same id are the same track, which is when they share their name,
+/// a named clip or sample or their devices. A renamed track without either is seen as replaced
+fn related(a: &Track, b: &Track) -> bool {
+    let named = |clip: &&Clip| !clip.name.is_empty() || clip.source.is_some();
+    let device_names = |track: &Track| -> Vec<String> {
+        track
+            .devices
+            .iter()
+            .map(|device| device.name.clone())
+            .collect()
+    };
+
+    a.kind == b.kind
+        && (a.name == b.name
+            || a.clips
+                .iter()
+                .filter(named)
+                .any(|a| b.clips.iter().any(|b| same_identity(a, b)))
+            || (!a.devices.is_empty() && device_names(a) == device_names(b)))
+}
+
+fn diff_clips(changes: &mut Vec<Change>, old: (usize, &Track), new: (usize, &Track)) {
+    let (old_track, old) = old;
+    let (new_track, new) = new;
+
+    let mut old_left: Vec<usize> = (0..old.clips.len()).collect();
+    let mut new_left: Vec<usize> = (0..new.clips.len()).collect();
+
+    // Unchanged clips
+    take_pairs(&mut old_left, &mut new_left, |o, n| {
+        let (o, n) = (&old.clips[o], &new.clips[n]);
+        same_identity(o, n) && o.start == n.start && o.end == n.end
+    });
+
+    // The same clip at a different place or with a different length
+    for (o, n) in take_pairs(&mut old_left, &mut new_left, |o, n| {
+        let (o, n) = (&old.clips[o], &new.clips[n]);
+        same_identity(o, n) && (o.start == n.start || o.end == n.end)
+    }) {
+        changes.push(Change::ClipResized {
+            old: (old_track, o),
+            new: (new_track, n),
+        });
+    }
+    for (o, n) in take_pairs(&mut old_left, &mut new_left, |o, n| {
+        let (o, n) = (&old.clips[o], &new.clips[n]);
+        same_identity(o, n) && clip_length(o) == clip_length(n)
+    }) {
+        changes.push(Change::ClipMoved {
+            old: (old_track, o),
+            new: (new_track, n),
+        });
+    }
+
+    // A different clip in exactly the same place
+    for (o, n) in take_pairs(&mut old_left, &mut new_left, |o, n| {
+        let (o, n) = (&old.clips[o], &new.clips[n]);
+        o.start == n.start && o.end == n.end
+    }) {
+        changes.push(Change::ClipRenamed {
+            old: (old_track, o),
+            new: (new_track, n),
+        });
+    }
+
+    for o in old_left {
+        changes.push(Change::ClipRemoved {
+            old: (old_track, o),
+        });
+    }
+    for n in new_left {
+        changes.push(Change::ClipAdded {
+            new: (new_track, n),
+        });
+    }
+}
+
+fn diff_devices(changes: &mut Vec<Change>, old: (usize, &Track), new: (usize, &Track)) {
+    let (old_track, old) = old;
+    let (new_track, new) = new;
+
+    let mut old_left: Vec<usize> = (0..old.devices.len()).collect();
+    let mut new_left: Vec<usize> = (0..new.devices.len()).collect();
+    // Switching a device on or off is not adding or removing it
+    take_pairs(&mut old_left, &mut new_left, |o, n| {
+        (&old.devices[o].name, &old.devices[o].kind) == (&new.devices[n].name, &new.devices[n].kind)
+    });
+
+    for o in old_left {
+        changes.push(Change::DeviceRemoved {
+            old_track,
+            device: old.devices[o].name.clone(),
+        });
+    }
+    for n in new_left {
+        changes.push(Change::DeviceAdded {
+            new_track,
+            device: new.devices[n].name.clone(),
+        });
+    }
+}
+
+/// Removes and returns every pair of indexes left in both lists which `matches`,
+/// each index is used at most once
+fn take_pairs(
+    old_left: &mut Vec<usize>,
+    new_left: &mut Vec<usize>,
+    matches: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    old_left.retain(|&o| match new_left.iter().position(|&n| matches(o, n)) {
+        Some(position) => {
+            pairs.push((o, new_left.remove(position)));
+            false
+        }
+        None => true,
+    });
+    pairs
+}
+
+/// Clips are treated as the same clip when their names match, or for audio clips their samples
+fn same_identity(a: &Clip, b: &Clip) -> bool {
+    a.kind == b.kind
+        && if a.name.is_empty() && b.name.is_empty() {
+            a.source == b.source
+        } else {
+            a.name == b.name
+        }
+}
+
+fn clip_length(clip: &Clip) -> f32 {
+    clip.end - clip.start
+}
+
+fn clip_name(clip: &Clip) -> String {
+    if clip.name.is_empty() {
+        "(untitled)".to_string()
+    } else {
+        format!("\"{}\"", clip.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{
+        als::TimeSignature, clip::ClipKind, device::Device, routing::TrackRouting,
+        track::TrackKind, version::LiveVersion,
+    };
+
+    fn set(tracks: Vec<Track>) -> AlsData {
+        AlsData {
+            name: "Song".to_string(),
+            path: String::new(),
+            modified: 0,
+            file_size: 0,
+            tempo: 120.,
+            time_signature: TimeSignature::default(),
+            version: LiveVersion::default(),
+            groups: Vec::new(),
+            tracks,
+            salvaged: false,
+        }
+    }
+
+    fn track(id: i32, name: &str, clips: Vec<Clip>) -> Track {
+        Track {
+            id,
+            kind: TrackKind::Midi,
+            group_id: -1,
+            name: name.to_string(),
+            color: Some(1),
+            clips,
+            devices: Vec::new(),
+            take_lanes: Vec::new(),
+            sends: Vec::new(),
+            routing: TrackRouting::default(),
+            sidechains: Vec::new(),
+            muted: false,
+            frozen: false,
+        }
+    }
+
+    fn clip(name: &str, start: f32, end: f32) -> Clip {
+        Clip {
+            name: name.to_string(),
+            kind: ClipKind::Midi,
+            start,
+            end,
+            loop_data: None,
+            source: None,
+            disabled: false,
+        }
+    }
+
+    fn changes(old: Vec<Track>, new: Vec<Track>) -> Vec<Change> {
+        diff(&set(old), &set(new)).changes
+    }
+
+    #[test]
+    fn unchanged() {
+        let tracks = || vec![track(1, "Bass", vec![clip("Verse", 0., 4.)])];
+        assert_eq!(changes(tracks(), tracks()), []);
+    }
+
+    #[test]
+    fn renamed_track() {
+        assert_eq!(
+            changes(
+                vec![track(1, "Bass", vec![clip("Verse", 0., 4.)])],
+                vec![track(1, "Sub", vec![clip("Verse", 0., 4.)])],
+            ),
+            [Change::TrackRenamed { old: 0, new: 0 }]
+        );
+
+        // A track without clips is still recognised by its devices
+        let with_synth = |name| {
+            let mut track = track(1, name, Vec::new());
+            track.devices.push(Device {
+                name: "Operator".to_string(),
+                kind: "Operator".to_string(),
+                on: true,
+            });
+            track
+        };
+        assert_eq!(
+            changes(vec![with_synth("Lead")], vec![with_synth("Pluck")]),
+            [Change::TrackRenamed { old: 0, new: 0 }]
+        );
+    }
+
+    #[test]
+    fn reordered_tracks() {
+        assert_eq!(
+            changes(
+                vec![
+                    track(1, "Drums", vec![]),
+                    track(2, "Bass", vec![]),
+                    track(3, "Keys", vec![]),
+                ],
+                vec![
+                    track(2, "Bass", vec![]),
+                    track(3, "Keys", vec![]),
+                    track(1, "Drums", vec![]),
+                ],
+            ),
+            [Change::TrackMoved { old: 0, new: 2 }]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_tracks() {
+        assert_eq!(
+            changes(
+                vec![track(1, "Drums", vec![]), track(2, "Bass", vec![])],
+                vec![track(1, "Drums", vec![]), track(3, "Vocals", vec![])],
+            ),
+            [
+                Change::TrackAdded { new: 1 },
+                Change::TrackRemoved { old: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_resized_and_added_clips() {
+        assert_eq!(
+            changes(
+                vec![track(
+                    1,
+                    "Keys",
+                    vec![clip("Verse", 0., 4.), clip("Chorus", 8., 12.)]
+                )],
+                vec![track(
+                    1,
+                    "Keys",
+                    vec![
+                        clip("Verse", 16., 20.),
+                        clip("Chorus", 8., 16.),
+                        clip("Outro", 32., 36.),
+                    ]
+                )],
+            ),
+            [
+                Change::ClipResized {
+                    old: (0, 1),
+                    new: (0, 1),
+                },
+                Change::ClipMoved {
+                    old: (0, 0),
+                    new: (0, 0),
+                },
+                Change::ClipAdded { new: (0, 2) },
+            ]
+        );
+    }
+
+    #[test]
+    fn unrelated_sets_sharing_ids() {
+        // Live numbers the tracks of every new set the same way
+        assert_eq!(
+            changes(
+                vec![track(8, "Drums", vec![clip("Beat", 0., 4.)])],
+                vec![track(8, "Vocals", vec![clip("Take 1", 0., 16.)])],
+            ),
+            [
+                Change::TrackAdded { new: 0 },
+                Change::TrackRemoved { old: 0 },
+            ]
+        );
+    }
+}
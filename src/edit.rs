@@ -0,0 +1,343 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use roxmltree::{Document, Node};
+use std::{
+    fs,
+    io::{Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::colors;
+use crate::extract;
+use crate::parse::{
+    als::{self, AlsData},
+    track::{self, TrackKind},
+};
+
+const EDITED_SUFFIX: &str = " (edited)";
+
+/// A change to a set which `EditableSet` makes to its *xml*
+#[derive(Debug, Clone)]
+pub enum Edit {
+    RenameTrack {
+        track: TrackRef,
+        name: String,
+    },
+    RecolourTrack {
+        track: TrackRef,
+        color: usize,
+    },
+    /// `clip` indexes the track's clips in the order `Track::clips` lists them
+    RecolourClip {
+        track: TrackRef,
+        clip: usize,
+        color: usize,
+    },
+    SetTempo(f32),
+    DeleteTrack(TrackRef),
+    /// Points every reference to the sample at `from` to a file within the project instead
+    RelinkSample {
+        from: String,
+        path: String,
+        /// Relative to the project directory
+        relative_path: String,
+    },
+}
+
+/// A track picked by its name or, written as `#12`, by its id
+#[derive(Debug, Clone)]
+pub enum TrackRef {
+    Id(i32),
+    Name(String),
+}
+
+impl FromStr for TrackRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('#') {
+            Some(id) => id
+                .parse()
+                .map(TrackRef::Id)
+                .map_err(|_| format!("Invalid track id \"{}\"", id)),
+            None => Ok(TrackRef::Name(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for TrackRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TrackRef::Id(id) => write!(f, "#{}", id),
+            TrackRef::Name(name) => write!(f, "\"{}\"", name),
+        }
+    }
+}
+
+impl TrackRef {
+    /// Index of the track in `AlsData::tracks`, names have to be unique to be used
+    fn resolve(&self, als_data: &AlsData) -> Result<usize, String> {
+        let mut matches = als_data
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| match self {
+                TrackRef::Id(id) => track.id == *id,
+                TrackRef::Name(name) => track.name == *name,
+            })
+            .map(|(index, _)| index);
+
+        match (matches.next(), matches.next()) {
+            (Some(index), None) => Ok(index),
+            (None, _) => Err(format!("No track {} in {}", self, als_data.name)),
+            (Some(_), Some(_)) => Err(format!(
+                "Several tracks are named {} in {}, use its id instead",
+                self, als_data.name
+            )),
+        }
+    }
+}
+
+/// The original *xml* of a set along with what palsa parses from it. Edits replace only
+/// the text they change so everything palsa does not model is kept exactly as it was
+pub struct EditableSet {
+    xml: String,
+    /// What parsing the edited *xml* should give, checked before saving
+    expected: AlsData,
+}
+
+impl EditableSet {
+    pub fn open(als_path: &Path) -> Result<EditableSet, String> {
+        // A salvaged part of a damaged set would be written back as if it were all of it
+        let xml = extract::extract_strict(als_path)
+            .map_err(|e| format!("Failed to extract {}: {}", als_path.display(), e))?;
+        let name = als_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let expected = AlsData::parse(name, xml.clone());
+
+        Ok(EditableSet { xml, expected })
+    }
+
+    /// Applies edits together, failing without changing anything
+    /// if any edit does not apply or two edits change the same text
+    pub fn apply(&mut self, edits: &[Edit]) -> Result<(), String> {
+        let doc = Document::parse(&self.xml).map_err(|e| e.to_string())?;
+        let root = doc.root_element();
+        let profile = self.expected.version.profile();
+        // The track nodes in the same order `AlsData::parse` lists them
+        let tracks = track::track_nodes(root, profile);
+
+        let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+        let mut expected = self.expected.clone();
+        let mut deleted = Vec::new();
+
+        for edit in edits {
+            match edit {
+                Edit::RenameTrack { track, name } => {
+                    let index = track.resolve(&self.expected)?;
+                    let name_node = child(tracks[index], "Name")?;
+                    for tag_name in ["EffectiveName", "UserName"] {
+                        let range = value_range(&self.xml, child(name_node, tag_name)?)?;
+                        replacements.push((range, escape_attribute(name)));
+                    }
+                    expected.tracks[index].name = name.clone();
+                }
+                Edit::RecolourTrack { track, color } => {
+                    colors::check_index(*color)?;
+                    let index = track.resolve(&self.expected)?;
+                    let range = value_range(&self.xml, child(tracks[index], profile.color)?)?;
+                    replacements.push((range, color.to_string()));
+                    expected.tracks[index].color = Some(*color);
+                }
+                Edit::RecolourClip { track, clip, color } => {
+                    colors::check_index(*color)?;
+                    let index = track.resolve(&self.expected)?;
+                    let clip_node = track::clip_nodes(tracks[index], profile)
+                        .nth(*clip)
+                        .ok_or_else(|| format!("Track {} has no clip {}", track, clip))?;
+                    let range = value_range(&self.xml, child(clip_node, profile.color)?)?;
+                    replacements.push((range, color.to_string()));
+                }
+                Edit::SetTempo(tempo) => {
+                    als::check_tempo(*tempo)?;
+                    let manual = root
+                        .descendants()
+                        .find(|n| n.has_tag_name(profile.main_track))
+                        .and_then(|n| n.descendants().find(|n| n.has_tag_name("Tempo")))
+                        .ok_or("The set has no master tempo")?;
+                    let range = value_range(&self.xml, child(manual, "Manual")?)?;
+                    replacements.push((range, tempo.to_string()));
+                    expected.tempo = *tempo;
+                }
+                Edit::DeleteTrack(track) => {
+                    let index = track.resolve(&self.expected)?;
+                    // Every track has a send to each return track so those cannot be removed,
+                    // nor can the master and prehear tracks which every set has
+                    match self.expected.tracks[index].kind {
+                        TrackKind::Return => {
+                            return Err(format!("Return track {} cannot be deleted", track))
+                        }
+                        TrackKind::Master | TrackKind::PreHear => {
+                            return Err(format!("Track {} cannot be deleted", track))
+                        }
+                        TrackKind::Midi | TrackKind::Audio => {}
+                    }
+                    let range = tracks[index].range();
+                    // Also removes the line break and indentation before the track
+                    let start = self.xml[..range.start].trim_end().len();
+                    replacements.push((start..range.end, String::new()));
+                    deleted.push(index);
+                }
+                Edit::RelinkSample {
+                    from,
+                    path,
+                    relative_path,
+                } => {
+                    let file_refs: Vec<Node> = root
+                        .descendants()
+                        .filter(|n| n.has_tag_name("SampleRef"))
+                        .filter_map(|n| n.children().find(|n| n.has_tag_name("FileRef")))
+                        .filter(|n| {
+                            child(*n, "Path").is_ok_and(|n| n.attribute("Value") == Some(from))
+                        })
+                        .collect();
+                    if file_refs.is_empty() {
+                        return Err(format!("No sample is at {}", from));
+                    }
+
+                    for file_ref in file_refs {
+                        for (tag_name, value) in [
+                            // The type of path which is relative to the project
+                            ("RelativePathType", "3"),
+                            ("RelativePath", relative_path),
+                            ("Path", path),
+                        ] {
+                            let range = value_range(&self.xml, child(file_ref, tag_name)?)?;
+                            replacements.push((range, escape_attribute(value)));
+                        }
+                    }
+                    for clip in expected.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()) {
+                        if clip.source.as_deref() == Some(from) {
+                            clip.source = Some(path.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        deleted.sort_unstable();
+        for index in deleted.into_iter().rev() {
+            expected.tracks.remove(index);
+        }
+
+        replacements.sort_by_key(|(range, _)| range.start);
+        if let Some(overlap) = replacements
+            .windows(2)
+            .find(|pair| pair[0].0.end > pair[1].0.start)
+        {
+            return Err(format!(
+                "Edits overlap at byte {} of the xml, such as renaming a deleted track",
+                overlap[1].0.start
+            ));
+        }
+
+        let mut xml = self.xml.clone();
+        for (range, text) in replacements.into_iter().rev() {
+            xml.replace_range(range, &text);
+        }
+
+        drop(doc);
+        self.xml = xml;
+        self.expected = expected;
+        Ok(())
+    }
+
+    /// The original or edited *xml* of the set
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+
+    /// Compresses the edited *xml* into an *als* file, first checking that decompressing and
+    /// parsing it again gives exactly what the edits should have changed and nothing else
+    pub fn save(&self, output: &Path) -> Result<(), String> {
+        fs::write(output, self.to_als()?)
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+    }
+
+    /// The verified contents of the *als* file `save` writes
+    pub fn to_als(&self) -> Result<Vec<u8>, String> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(self.xml.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map_err(|e| e.to_string())?;
+        self.verify(&compressed)?;
+        Ok(compressed)
+    }
+
+    fn verify(&self, compressed: &[u8]) -> Result<(), String> {
+        let mut xml = String::new();
+        GzDecoder::new(compressed)
+            .read_to_string(&mut xml)
+            .map_err(|e| format!("Verifying the edited set failed: {}", e))?;
+        Document::parse(&xml).map_err(|e| format!("The edited set is not valid xml: {}", e))?;
+
+        let parsed = AlsData::parse(self.expected.name.clone(), xml);
+        let to_yaml = |als_data: &AlsData| serde_yaml::to_string(als_data).unwrap_or_default();
+        if to_yaml(&parsed) != to_yaml(&self.expected) {
+            return Err("The edited set does not parse to what the edits should give".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Where an edited copy of a set is saved by default, next to the original
+pub fn edited_path(als_path: &Path) -> PathBuf {
+    let name = als_path.file_stem().unwrap_or_default().to_string_lossy();
+    als_path.with_file_name(format!("{}{}.als", name, EDITED_SUFFIX))
+}
+
+/// Whether a set is a copy saved by an earlier edit
+pub fn is_edited_copy(als_path: &Path) -> bool {
+    als_path
+        .file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().ends_with(EDITED_SUFFIX))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag_name: &str) -> Result<Node<'a, 'input>, String> {
+    node.children()
+        .find(|n| n.has_tag_name(tag_name))
+        .ok_or_else(|| format!("{} has no {} element", node.tag_name().name(), tag_name))
+}
+
+/// Byte range of the text of a node's `Value` attribute within the *xml*
+fn value_range(xml: &str, node: Node) -> Result<Range<usize>, String> {
+    let attribute = node
+        .attribute_node("Value")
+        .ok_or_else(|| format!("{} has no value", node.tag_name().name()))?;
+
+    // The position points at the attribute's name, which is followed by `=` and a quote
+    let after_name = attribute.position() + "Value".len();
+    let equals = after_name + xml[after_name..].find('=').ok_or("Malformed attribute")?;
+    let quote_position = equals
+        + 1
+        + xml[equals + 1..]
+            .find(['"', '\''])
+            .ok_or("Malformed attribute")?;
+    let quote = xml.as_bytes()[quote_position] as char;
+    let start = quote_position + 1;
+    let end = start + xml[start..].find(quote).ok_or("Malformed attribute")?;
+
+    Ok(start..end)
+}
+
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,255 @@
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Cursor},
+    path::{self, Path, PathBuf},
+};
+
+use super::zip::ZipWriter;
+use crate::edit::{Edit, EditableSet};
+use crate::parallel;
+use crate::samples::{self, Location};
+use crate::search;
+
+/// Where samples from outside the project are put, like live's *Collect All and Save* does
+const IMPORTED_DIR: &str = "Samples/Imported";
+
+/// What went into an archive, stored in it as `manifest.json`
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub project: String,
+    /// When the archive was made, in seconds since the unix epoch
+    pub created: u64,
+    pub sets: Vec<String>,
+    /// Samples from outside the project which were copied into it
+    pub collected: Vec<CollectedSample>,
+    /// Samples which could not be found, the sets still point to where they used to be
+    pub missing: Vec<String>,
+    /// Samples of live's core library, which every installation has so they are not archived
+    pub core_library: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectedSample {
+    pub original: String,
+    /// Relative to the project directory
+    pub archived: String,
+    pub size: u64,
+}
+
+/// Collects every sample the project's sets use from outside of it into `Samples/Imported`
+/// and writes the project to a *zip* archive at `output`, with the sets pointing to the
+/// collected samples. Only the archive is written, the project itself is left as it is.
+/// The `Backup` directory is left out as its sets still point to the original samples
+pub fn archive(project_dir: &Path, output: &Path) -> Result<Manifest, String> {
+    let name = project_dir
+        .file_name()
+        .ok_or_else(|| format!("Invalid project directory {}", project_dir.display()))?
+        .to_string_lossy()
+        .to_string();
+    let canonical_dir = project_dir.canonicalize().map_err(|e| e.to_string())?;
+    let absolute_dir = path::absolute(project_dir).map_err(|e| e.to_string())?;
+
+    let mut set_paths: Vec<PathBuf> = parallel::find_projects(&project_dir.to_string_lossy())
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    set_paths.sort();
+
+    let mut manifest = Manifest {
+        project: name.clone(),
+        created: search::now(),
+        sets: Vec::new(),
+        collected: Vec::new(),
+        missing: Vec::new(),
+        core_library: Vec::new(),
+    };
+    // Where each sample from outside the project goes, shared by all sets
+    let mut collected: HashMap<PathBuf, String> = HashMap::new();
+    let mut sets = Vec::new();
+
+    for set_path in &set_paths {
+        let mut set = EditableSet::open(set_path)?;
+        let mut edits = Vec::new();
+        // The same file may be referenced by several kinds of paths, one edit relinks them all
+        let mut relinked = HashSet::new();
+
+        let references =
+            samples::references(set.xml()).map_err(|e| format!("{}: {}", set_path.display(), e))?;
+        for reference in references {
+            if reference.location == Location::CoreLibrary {
+                manifest
+                    .core_library
+                    .push(reference.shown_path().to_string());
+                continue;
+            }
+            let Some(file) = reference.resolve(project_dir, project_dir) else {
+                manifest.missing.push(reference.shown_path().to_string());
+                continue;
+            };
+            let file = file.canonicalize().map_err(|e| e.to_string())?;
+
+            if reference.legacy {
+                // Paths relative to the set or project stay valid as the project's layout is kept
+                if file.starts_with(&canonical_dir)
+                    && matches!(reference.location, Location::Project | Location::External)
+                {
+                    continue;
+                }
+                return Err(format!(
+                    "{}: {} is referenced the way live 10 and earlier do, which can not be \
+                     pointed to a collected copy. Save the set in live 11 or later first",
+                    set_path.display(),
+                    reference.shown_path()
+                ));
+            }
+
+            let relative_path = match file.strip_prefix(&canonical_dir) {
+                // Already within the project, only absolute references need relinking
+                Ok(_) if reference.location == Location::Project => continue,
+                Ok(relative) => to_relative_path(relative),
+                Err(_) => match collected.get(&file) {
+                    Some(relative_path) => relative_path.clone(),
+                    None => {
+                        let relative_path = unused_path(&file, project_dir, &collected);
+                        manifest.collected.push(CollectedSample {
+                            original: file.to_string_lossy().to_string(),
+                            archived: relative_path.clone(),
+                            size: fs::metadata(&file).map_or(0, |metadata| metadata.len()),
+                        });
+                        collected.insert(file, relative_path.clone());
+                        relative_path
+                    }
+                },
+            };
+
+            if !relinked.insert(reference.path.clone()) {
+                continue;
+            }
+            edits.push(Edit::RelinkSample {
+                from: reference.path,
+                path: absolute_dir
+                    .join(&relative_path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                relative_path,
+            });
+        }
+
+        set.apply(&edits)
+            .map_err(|e| format!("{}: {}", set_path.display(), e))?;
+        let file_name = set_path.file_name().unwrap_or_default().to_string_lossy();
+        manifest.sets.push(file_name.to_string());
+        sets.push((file_name.to_string(), set.to_als()?));
+    }
+
+    for list in [&mut manifest.missing, &mut manifest.core_library] {
+        list.sort();
+        list.dedup();
+    }
+
+    write_zip(project_dir, output, &name, &sets, &collected, &manifest)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    Ok(manifest)
+}
+
+fn write_zip(
+    project_dir: &Path,
+    output: &Path,
+    name: &str,
+    sets: &[(String, Vec<u8>)],
+    collected: &HashMap<PathBuf, String>,
+    manifest: &Manifest,
+) -> std::io::Result<()> {
+    let output_file = File::create(output)?;
+    // Checked after creating it so the archive is found when it is written into the project
+    let canonical_output = output.canonicalize()?;
+    let mut zip = ZipWriter::new(BufWriter::new(output_file), manifest.created);
+
+    for (file_name, contents) in sets {
+        let path = format!("{}/{}", name, file_name);
+        zip.add_file(&path, Cursor::new(contents), contents.len() as u64)?;
+    }
+
+    for file in project_files(project_dir)? {
+        let relative = file.strip_prefix(project_dir).unwrap_or(&file);
+        let is_set = relative.parent() == Some(Path::new(""))
+            && sets
+                .iter()
+                .any(|(set, _)| relative.as_os_str() == set.as_str());
+        if is_set || file.canonicalize()? == canonical_output {
+            continue;
+        }
+        let path = format!("{}/{}", name, to_relative_path(relative));
+        zip.add_file(&path, File::open(&file)?, fs::metadata(&file)?.len())?;
+    }
+
+    let mut collected: Vec<_> = collected.iter().collect();
+    collected.sort_by(|a, b| a.1.cmp(b.1));
+    for (file, relative_path) in collected {
+        let path = format!("{}/{}", name, relative_path);
+        zip.add_file(&path, File::open(file)?, fs::metadata(file)?.len())?;
+    }
+
+    let manifest_json = serde_json::to_string_pretty(manifest)?;
+    zip.add_file(
+        &format!("{}/manifest.json", name),
+        manifest_json.as_bytes(),
+        manifest_json.len() as u64,
+    )?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Every file within the project apart from its `Backup` directory
+fn project_files(project_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![project_dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if path != project_dir.join("Backup") {
+                    dirs.push(path);
+                }
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// A path within `Samples/Imported` for a sample, numbering it
+/// when a different file of the same name is already there
+fn unused_path(file: &Path, project_dir: &Path, collected: &HashMap<PathBuf, String>) -> String {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = file
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|number| match number {
+            1 => format!("{}/{}{}", IMPORTED_DIR, stem, extension),
+            number => format!("{}/{} {}{}", IMPORTED_DIR, stem, number, extension),
+        })
+        .find(|path| {
+            !project_dir.join(path).exists() && !collected.values().any(|taken| taken == path)
+        })
+        .unwrap()
+}
+
+/// Joins the components of a path with `/` like live and *zip* archives do on every platform
+fn to_relative_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,73 @@
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+
+use crate::parse::{
+    als::{AlsData, Project},
+    track::TrackKind,
+};
+
+/// Bumped whenever a change to `AlsData` or the types within it
+/// could break something reading the exported json
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The root of every exported json document
+#[derive(Serialize, JsonSchema)]
+pub struct JsonExport {
+    /// Version of the schema the document follows
+    pub schema_version: u32,
+    pub projects: Vec<JsonProject>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonProject {
+    /// Name of the project directory
+    pub name: String,
+    /// The project's sets, leaving out the prehear track which live names `Master` too
+    pub sets: Vec<AlsData>,
+    /// Older versions of the sets found in the project's `Backup` directory, also without
+    /// their prehear track
+    pub backups: Vec<AlsData>,
+}
+
+impl JsonExport {
+    pub fn new(projects: Vec<Project>) -> JsonExport {
+        JsonExport {
+            schema_version: SCHEMA_VERSION,
+            projects: projects
+                .into_iter()
+                .map(|project| JsonProject {
+                    name: project.name,
+                    sets: without_prehear(project.als_data.unwrap_or_default()),
+                    backups: without_prehear(project.backups),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to create json: {}", e))
+    }
+}
+
+/// Live only uses the prehear track to preview samples, it is not part of the set
+fn without_prehear(mut sets: Vec<AlsData>) -> Vec<AlsData> {
+    for als_data in &mut sets {
+        als_data
+            .tracks
+            .retain(|track| track.kind != TrackKind::PreHear);
+    }
+    sets
+}
+
+/// The json schema of `JsonExport`, generated from the parsed types and their doc comments
+pub fn schema() -> RootSchema {
+    let mut schema = schema_for!(JsonExport);
+    let metadata = schema.schema.metadata();
+    metadata.id = Some(format!("palsa-v{}.schema.json", SCHEMA_VERSION));
+    metadata.title = Some(format!("Palsa export v{}", SCHEMA_VERSION));
+    schema
+}
+
+pub fn schema_json() -> String {
+    serde_json::to_string_pretty(&schema()).expect("Failed to convert the json schema to a string!")
+}
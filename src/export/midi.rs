@@ -0,0 +1,312 @@
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+use roxmltree::{Document, Node};
+use std::path::Path;
+
+use crate::extract;
+use crate::get_attribute_value;
+use crate::parse::{
+    als::{self, TimeSignature},
+    clip::Clip,
+    note::Note,
+    version::LiveVersion,
+};
+
+/// Ticks per quarter note of the exported file
+const TICKS_PER_BEAT: u16 = 480;
+
+/// A note placed in the arrangement, in quarter note beats
+struct PlacedNote {
+    key: u8,
+    start: f64,
+    end: f64,
+    velocity: u8,
+}
+
+/// Creates a standard midi file from the active arrangement midi clips of an *als* file,
+/// with one midi track for each of the set's midi tracks
+/// along with the master tempo and time signature (tempo automation is ignored).
+/// Also gives whether the set was damaged, so only the clips which could be salvaged are in it
+pub fn export_midi(als_path: &Path) -> Result<(Vec<u8>, bool), String> {
+    let extracted = extract::extract(als_path.to_string_lossy().to_string())
+        .map_err(|e| format!("Failed to extract {}: {}", als_path.display(), e))?;
+    let doc = Document::parse(&extracted.xml).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+
+    let (tempo, time_signature) =
+        als::parse_master_timing(root, LiveVersion::parse(root).profile());
+
+    let tracks: Vec<(String, Vec<PlacedNote>)> = root
+        .descendants()
+        .filter(|n| n.has_tag_name("MidiTrack"))
+        .map(|track| {
+            let name = get_attribute_value!(track, "Name", "EffectiveName");
+            let notes = track
+                .descendants()
+                .filter(|n| n.has_tag_name("MidiClip") && is_arrangement_clip(*n))
+                .map(|n| (Clip::parse(n), n))
+                // Live does not play deactivated clips
+                .filter(|(clip, _)| !clip.disabled)
+                .flat_map(|(clip, n)| place_notes(&clip, Note::parse_clip(n)))
+                .collect();
+            (name, notes)
+        })
+        .collect();
+
+    let mut smf = Smf::new(Header::new(
+        Format::Parallel,
+        Timing::Metrical(u15::new(TICKS_PER_BEAT)),
+    ));
+    smf.tracks.push(conductor_track(tempo, time_signature));
+    for (i, (name, notes)) in tracks.iter().enumerate() {
+        smf.tracks.push(note_track(name, notes, i));
+    }
+
+    let mut bytes = Vec::new();
+    smf.write_std(&mut bytes)
+        .map_err(|e| format!("Failed to write midi file: {}", e))?;
+    Ok((bytes, extracted.salvaged))
+}
+
+/// Session view clips are stored in clip slots rather than the arrangement
+fn is_arrangement_clip(node: Node) -> bool {
+    node.ancestors()
+        .any(|n| n.has_tag_name("ArrangerAutomation"))
+}
+
+/// Places a clip's notes in the arrangement, repeating the loop region until the clip ends
+/// when looping is on and otherwise playing once from the loop start. While looping is off live
+/// stores the clip's start marker in `LoopStart` and keeps the loop brace in `HiddenLoopStart`,
+/// so there is no separate start marker to parse
+fn place_notes(clip: &Clip, notes: Vec<Note>) -> Vec<PlacedNote> {
+    let (start, end) = (clip.start as f64, clip.end as f64);
+    let Some(ref loop_data) = clip.loop_data else {
+        return Vec::new();
+    };
+    // A hand edited clip which never ends would repeat its loop forever
+    if !end.is_finite() {
+        return Vec::new();
+    }
+
+    // Each segment plays `from..to` of the clip's contents starting at `offset` in the arrangement
+    let mut segments: Vec<(f64, f64, f64)> = Vec::new();
+    let loop_length = loop_data.end - loop_data.start;
+    if loop_data.on && loop_length > 0. {
+        // Playback can start anywhere, hand edited sets may put it past the loop end
+        let mut from = loop_data.start + loop_data.start_relative.rem_euclid(loop_length);
+        let mut offset = start;
+        while offset < end {
+            let to = (loop_data.end).min(from + end - offset);
+            if to <= from {
+                break;
+            }
+            segments.push((from, to, offset));
+            offset += to - from;
+            from = loop_data.start;
+        }
+    } else {
+        segments.push((loop_data.start, loop_data.start + end - start, start));
+    }
+
+    segments
+        .iter()
+        .flat_map(|&(from, to, offset)| {
+            notes
+                .iter()
+                .filter(move |note| note.time >= from && note.time < to)
+                .map(move |note| PlacedNote {
+                    key: note.key,
+                    start: offset + note.time - from,
+                    // Notes are cut off at the end of the loop or clip like in ableton
+                    end: offset + (note.time + note.duration).min(to) - from,
+                    velocity: note.velocity,
+                })
+        })
+        .collect()
+}
+
+fn conductor_track(tempo: f32, time_signature: TimeSignature) -> Vec<TrackEvent<'static>> {
+    let microseconds_per_beat = (60_000_000. / tempo as f64).round() as u32;
+
+    vec![
+        meta(0, MetaMessage::Tempo(u24::new(microseconds_per_beat))),
+        meta(
+            0,
+            MetaMessage::TimeSignature(
+                time_signature.numerator as u8,
+                time_signature.denominator.trailing_zeros() as u8,
+                24,
+                8,
+            ),
+        ),
+        meta(0, MetaMessage::EndOfTrack),
+    ]
+}
+
+fn note_track<'a>(name: &'a str, notes: &[PlacedNote], index: usize) -> Vec<TrackEvent<'a>> {
+    // Tracks get their own channels, skipping the general midi drum channel
+    let channel = u4::new([0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15][index % 15]);
+
+    let mut events: Vec<(u64, MidiMessage)> = notes
+        .iter()
+        .flat_map(|note| {
+            let key = u7::new(note.key.min(127));
+            [
+                (
+                    to_ticks(note.start),
+                    MidiMessage::NoteOn {
+                        key,
+                        vel: u7::new(note.velocity.clamp(1, 127)),
+                    },
+                ),
+                (
+                    to_ticks(note.end),
+                    MidiMessage::NoteOff {
+                        key,
+                        vel: u7::new(64),
+                    },
+                ),
+            ]
+        })
+        .collect();
+
+    // Note offs go first so repeated notes on the same key are not cut short
+    events.sort_by_key(|(tick, message)| (*tick, matches!(message, MidiMessage::NoteOn { .. })));
+
+    let mut track = vec![meta(0, MetaMessage::TrackName(name.as_bytes()))];
+    let mut last_tick = 0;
+    for (tick, message) in events {
+        track.push(TrackEvent {
+            delta: u28::new((tick - last_tick) as u32),
+            kind: TrackEventKind::Midi { channel, message },
+        });
+        last_tick = tick;
+    }
+    track.push(meta(0, MetaMessage::EndOfTrack));
+    track
+}
+
+fn meta(delta: u32, message: MetaMessage) -> TrackEvent {
+    TrackEvent {
+        delta: u28::new(delta),
+        kind: TrackEventKind::Meta(message),
+    }
+}
+
+fn to_ticks(beats: f64) -> u64 {
+    (beats.max(0.) * TICKS_PER_BEAT as f64).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::clip::{ClipKind, Loop};
+
+    fn clip(start: f32, end: f32, loop_data: Loop) -> Clip {
+        Clip {
+            name: String::new(),
+            kind: ClipKind::Midi,
+            start,
+            end,
+            loop_data: Some(loop_data),
+            source: None,
+            disabled: false,
+        }
+    }
+
+    fn note(time: f64, duration: f64) -> Note {
+        Note {
+            key: 60,
+            time,
+            duration,
+            velocity: 100,
+        }
+    }
+
+    fn placed(clip: &Clip, notes: Vec<Note>) -> Vec<(f64, f64)> {
+        place_notes(clip, notes)
+            .iter()
+            .map(|note| (note.start, note.end))
+            .collect()
+    }
+
+    #[test]
+    fn looped_clip_repeats_until_it_ends() {
+        let looped = clip(
+            8.,
+            18.,
+            Loop {
+                start: 0.,
+                end: 4.,
+                on: true,
+                start_relative: 0.,
+            },
+        );
+        // The note past the loop end is never played and the last one is cut off at the clip end
+        let notes = vec![note(0., 1.), note(3., 2.), note(5., 1.)];
+        assert_eq!(
+            placed(&looped, notes),
+            [(8., 9.), (11., 12.), (12., 13.), (15., 16.), (16., 17.),]
+        );
+    }
+
+    #[test]
+    fn unlooped_clip_plays_once_from_the_loop_start() {
+        let unlooped = clip(
+            4.,
+            6.,
+            Loop {
+                start: 1.,
+                end: 2.,
+                on: false,
+                start_relative: 0.,
+            },
+        );
+        let notes = vec![note(0., 1.), note(1., 0.5), note(2.5, 1.), note(3., 1.)];
+        assert_eq!(placed(&unlooped, notes), [(4., 4.5), (5.5, 6.)]);
+    }
+
+    #[test]
+    fn start_offset_plays_the_rest_of_the_loop_first() {
+        let offset = clip(
+            0.,
+            6.,
+            Loop {
+                start: 0.,
+                end: 4.,
+                on: true,
+                start_relative: 2.,
+            },
+        );
+        let notes = vec![note(0., 1.), note(2., 1.)];
+        assert_eq!(placed(&offset, notes), [(0., 1.), (2., 3.), (4., 5.)]);
+    }
+
+    #[test]
+    fn start_offset_past_the_loop_end_wraps_around() {
+        for (start_relative, expected) in [
+            (6., vec![(3., 3.5)]),
+            (-2., vec![(3., 3.5)]),
+            (4., vec![(1., 1.5), (5., 5.5)]),
+        ] {
+            let wrapped = clip(
+                0.,
+                6.,
+                Loop {
+                    start: 0.,
+                    end: 4.,
+                    on: true,
+                    start_relative,
+                },
+            );
+            assert_eq!(
+                placed(&wrapped, vec![note(1., 0.5)]),
+                expected,
+                "{}",
+                start_relative
+            );
+        }
+    }
+}
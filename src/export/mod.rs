@@ -0,0 +1,12 @@
+use std::{fs, path::PathBuf};
+
+pub mod archive;
+pub mod json;
+pub mod midi;
+pub mod zip;
+
+/// Creates the folder exports from the gui are written to and returns the path for a file in it
+pub fn export_path(name: &str, extension: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all("exports/")?;
+    Ok(PathBuf::from(format!("exports/{}.{}", name, extension)))
+}
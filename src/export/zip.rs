@@ -0,0 +1,369 @@
+use flate2::{write::DeflateEncoder, Compression, Crc};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::history::BackupTimestamp;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x06064b50;
+const ZIP64_END_LOCATOR: u32 = 0x07064b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+/// Set when names are UTF-8, which sample names often need
+const UTF8_FLAG: u16 = 1 << 11;
+const DEFLATE: u16 = 8;
+/// Files larger than this get zip64 sizes, leaving room for deflate making them slightly larger
+const ZIP64_THRESHOLD: u64 = 0xFFFF_0000;
+
+/// Writes a *zip* archive, deflating each file as it is added. Sizes and offsets which
+/// do not fit the original format are stored in *zip64* fields, so archives of whole
+/// projects larger than 4 GB still open
+pub struct ZipWriter<W: Write + Seek> {
+    out: W,
+    entries: Vec<Entry>,
+    /// The time and date every file is stored with, in the format of ms-dos
+    dos_time: (u16, u16),
+    /// Sizes and offsets above this are stored in zip64 fields
+    zip64_threshold: u64,
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    offset: u64,
+}
+
+impl<W: Write + Seek> ZipWriter<W> {
+    /// Files are stored as modified at `modified`, in seconds since the unix epoch
+    pub fn new(out: W, modified: u64) -> ZipWriter<W> {
+        let time = BackupTimestamp::from_unix(modified);
+        let dos_time = (
+            ((time.hour << 11) | (time.minute << 5) | (time.second / 2)) as u16,
+            ((((time.year - 1980).max(0) as u32) << 9) | (time.month << 5) | time.day) as u16,
+        );
+
+        ZipWriter {
+            out,
+            entries: Vec::new(),
+            dos_time,
+            zip64_threshold: ZIP64_THRESHOLD,
+        }
+    }
+
+    /// Adds a file of `size` bytes, `name` uses `/` between directories
+    pub fn add_file(&mut self, name: &str, mut contents: impl Read, size: u64) -> io::Result<()> {
+        let offset = self.out.stream_position()?;
+        let zip64 = size > self.zip64_threshold;
+
+        // The crc and sizes are filled in once the file was written
+        self.out.write_all(&LOCAL_HEADER.to_le_bytes())?;
+        self.out
+            .write_all(&(if zip64 { 45u16 } else { 20u16 }).to_le_bytes())?;
+        self.out.write_all(&UTF8_FLAG.to_le_bytes())?;
+        self.out.write_all(&DEFLATE.to_le_bytes())?;
+        self.out.write_all(&self.dos_time.0.to_le_bytes())?;
+        self.out.write_all(&self.dos_time.1.to_le_bytes())?;
+        let sizes_position = self.out.stream_position()?;
+        self.out.write_all(&[0; 12])?;
+        self.out.write_all(&(name.len() as u16).to_le_bytes())?;
+        self.out
+            .write_all(&(if zip64 { 20u16 } else { 0u16 }).to_le_bytes())?;
+        self.out.write_all(name.as_bytes())?;
+        if zip64 {
+            self.out.write_all(&ZIP64_EXTRA.to_le_bytes())?;
+            self.out.write_all(&16u16.to_le_bytes())?;
+            self.out.write_all(&[0; 16])?;
+        }
+
+        let data_start = self.out.stream_position()?;
+        let mut crc = Crc::new();
+        // `Crc::amount` is only 32 bits so it wraps around for files of 4 GiB and more
+        let mut read_size = 0u64;
+        let mut encoder = DeflateEncoder::new(&mut self.out, Compression::default());
+        let mut buf = vec![0; 1 << 16];
+        loop {
+            let read = contents.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            crc.update(&buf[..read]);
+            read_size += read as u64;
+            encoder.write_all(&buf[..read])?;
+        }
+        encoder.finish()?;
+        let data_end = self.out.stream_position()?;
+
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc.sum(),
+            compressed_size: data_end - data_start,
+            size: read_size,
+            offset,
+        };
+        if !zip64 && (entry.size > u32::MAX as u64 || entry.compressed_size > u32::MAX as u64) {
+            return Err(io::Error::other(format!(
+                "{} is larger than the {} bytes it was added as",
+                name, size
+            )));
+        }
+
+        self.out.seek(SeekFrom::Start(sizes_position))?;
+        self.out.write_all(&entry.crc.to_le_bytes())?;
+        if zip64 {
+            self.out.write_all(&u32::MAX.to_le_bytes())?;
+            self.out.write_all(&u32::MAX.to_le_bytes())?;
+            self.out.seek(SeekFrom::Start(data_start - 16))?;
+            self.out.write_all(&entry.size.to_le_bytes())?;
+            self.out.write_all(&entry.compressed_size.to_le_bytes())?;
+        } else {
+            self.out
+                .write_all(&(entry.compressed_size as u32).to_le_bytes())?;
+            self.out.write_all(&(entry.size as u32).to_le_bytes())?;
+        }
+        self.out.seek(SeekFrom::Start(data_end))?;
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory listing every file and returns the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_start = self.out.stream_position()?;
+
+        for entry in &self.entries {
+            // Values which do not fit are replaced by `u32::MAX` and stored in the extra field
+            let mut extra = Vec::new();
+            let threshold = self.zip64_threshold;
+            let mut field = |value: u64| {
+                if value > threshold {
+                    extra.extend_from_slice(&value.to_le_bytes());
+                    u32::MAX
+                } else {
+                    value as u32
+                }
+            };
+            let size = field(entry.size);
+            let compressed_size = field(entry.compressed_size);
+            let offset = field(entry.offset);
+            let version = if extra.is_empty() { 20u16 } else { 45u16 };
+
+            self.out.write_all(&CENTRAL_HEADER.to_le_bytes())?;
+            self.out.write_all(&version.to_le_bytes())?;
+            self.out.write_all(&version.to_le_bytes())?;
+            self.out.write_all(&UTF8_FLAG.to_le_bytes())?;
+            self.out.write_all(&DEFLATE.to_le_bytes())?;
+            self.out.write_all(&self.dos_time.0.to_le_bytes())?;
+            self.out.write_all(&self.dos_time.1.to_le_bytes())?;
+            self.out.write_all(&entry.crc.to_le_bytes())?;
+            self.out.write_all(&compressed_size.to_le_bytes())?;
+            self.out.write_all(&size.to_le_bytes())?;
+            self.out
+                .write_all(&(entry.name.len() as u16).to_le_bytes())?;
+            let extra_length = if extra.is_empty() { 0 } else { extra.len() + 4 };
+            self.out.write_all(&(extra_length as u16).to_le_bytes())?;
+            // Comment length, disk number and internal and external attributes
+            self.out.write_all(&[0; 10])?;
+            self.out.write_all(&offset.to_le_bytes())?;
+            self.out.write_all(entry.name.as_bytes())?;
+            if !extra.is_empty() {
+                self.out.write_all(&ZIP64_EXTRA.to_le_bytes())?;
+                self.out.write_all(&(extra.len() as u16).to_le_bytes())?;
+                self.out.write_all(&extra)?;
+            }
+        }
+
+        let directory_end = self.out.stream_position()?;
+        let count = self.entries.len() as u64;
+        let directory_size = directory_end - directory_start;
+        let zip64 = count >= u16::MAX as u64
+            || directory_size >= u32::MAX as u64
+            || directory_start >= u32::MAX as u64;
+
+        if zip64 {
+            self.out
+                .write_all(&ZIP64_END_OF_CENTRAL_DIRECTORY.to_le_bytes())?;
+            self.out.write_all(&44u64.to_le_bytes())?;
+            self.out.write_all(&45u16.to_le_bytes())?;
+            self.out.write_all(&45u16.to_le_bytes())?;
+            self.out.write_all(&[0; 8])?;
+            self.out.write_all(&count.to_le_bytes())?;
+            self.out.write_all(&count.to_le_bytes())?;
+            self.out.write_all(&directory_size.to_le_bytes())?;
+            self.out.write_all(&directory_start.to_le_bytes())?;
+
+            self.out.write_all(&ZIP64_END_LOCATOR.to_le_bytes())?;
+            self.out.write_all(&0u32.to_le_bytes())?;
+            self.out.write_all(&directory_end.to_le_bytes())?;
+            self.out.write_all(&1u32.to_le_bytes())?;
+        }
+
+        let clamp16 = |value: u64| value.min(u16::MAX as u64) as u16;
+        let clamp32 = |value: u64| value.min(u32::MAX as u64) as u32;
+        self.out
+            .write_all(&END_OF_CENTRAL_DIRECTORY.to_le_bytes())?;
+        self.out.write_all(&[0; 4])?;
+        self.out.write_all(&clamp16(count).to_le_bytes())?;
+        self.out.write_all(&clamp16(count).to_le_bytes())?;
+        self.out.write_all(&clamp32(directory_size).to_le_bytes())?;
+        self.out
+            .write_all(&clamp32(directory_start).to_le_bytes())?;
+        self.out.write_all(&0u16.to_le_bytes())?;
+
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Cursor;
+
+    const FILES: [(&str, &[u8]); 3] = [
+        ("Song.als", b"<Ableton />"),
+        ("Samples/Imported/Kick \u{e9}.wav", &[7; 100_000]),
+        ("empty.txt", b""),
+    ];
+
+    fn u16_at(zip: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(zip[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(zip: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(zip[at..at + 4].try_into().unwrap())
+    }
+
+    fn u64_at(zip: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(zip[at..at + 8].try_into().unwrap())
+    }
+
+    fn write_zip(zip64_threshold: u64) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()), 1_700_000_000);
+        writer.zip64_threshold = zip64_threshold;
+        for (name, contents) in FILES {
+            writer
+                .add_file(name, contents, contents.len() as u64)
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn crc(contents: &[u8]) -> u32 {
+        let mut crc = Crc::new();
+        crc.update(contents);
+        crc.sum()
+    }
+
+    /// The sizes and offset of an entry, read from the zip64 extra field where they are `u32::MAX`
+    fn central_values(zip: &[u8], at: usize) -> (u64, u64, u64) {
+        let name_length = u16_at(zip, at + 28) as usize;
+        let extra_length = u16_at(zip, at + 30) as usize;
+        let mut extra = at + 46 + name_length;
+        if extra_length > 0 {
+            assert_eq!(u16_at(zip, extra), ZIP64_EXTRA);
+            assert_eq!(u16_at(zip, extra + 2) as usize, extra_length - 4);
+            extra += 4;
+        }
+        let mut value = |position: usize| match u32_at(zip, at + position) {
+            u32::MAX => {
+                extra += 8;
+                u64_at(zip, extra - 8)
+            }
+            value => value as u64,
+        };
+        // The extra field holds the size before the compressed size
+        let size = value(24);
+        let compressed_size = value(20);
+        let offset = value(42);
+        (size, compressed_size, offset)
+    }
+
+    /// Reads every entry back from the central directory found through the end record
+    fn read_zip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(u16_at(zip, end + 10) as usize, FILES.len());
+        let directory_size = u32_at(zip, end + 12) as usize;
+        let directory_start = u32_at(zip, end + 16) as usize;
+        assert_eq!(directory_start + directory_size, end);
+
+        let mut files = Vec::new();
+        let mut at = directory_start;
+        for _ in 0..FILES.len() {
+            assert_eq!(u32_at(zip, at), CENTRAL_HEADER);
+            assert_eq!(u16_at(zip, at + 8), UTF8_FLAG);
+            assert_eq!(u16_at(zip, at + 10), DEFLATE);
+            let name_length = u16_at(zip, at + 28) as usize;
+            let name = String::from_utf8(zip[at + 46..at + 46 + name_length].to_vec()).unwrap();
+            let (size, compressed_size, offset) = central_values(zip, at);
+
+            let local = offset as usize;
+            assert_eq!(u32_at(zip, local), LOCAL_HEADER);
+            assert_eq!(u32_at(zip, local + 14), u32_at(zip, at + 16));
+            let local_name_length = u16_at(zip, local + 26) as usize;
+            let local_extra_length = u16_at(zip, local + 28) as usize;
+            assert_eq!(
+                &zip[local + 30..local + 30 + local_name_length],
+                name.as_bytes()
+            );
+            let data_start = local + 30 + local_name_length + local_extra_length;
+            let data = &zip[data_start..data_start + compressed_size as usize];
+
+            let mut contents = Vec::new();
+            DeflateDecoder::new(data)
+                .read_to_end(&mut contents)
+                .unwrap();
+            assert_eq!(contents.len() as u64, size);
+            assert_eq!(crc(&contents), u32_at(zip, at + 16));
+            files.push((name, contents));
+
+            at += 46 + name_length + u16_at(zip, at + 30) as usize;
+        }
+        assert_eq!(at, directory_start + directory_size);
+        files
+    }
+
+    #[test]
+    fn reads_back_every_file() {
+        let zip = write_zip(ZIP64_THRESHOLD);
+        let files = read_zip(&zip);
+        for ((name, contents), (expected_name, expected_contents)) in files.iter().zip(FILES) {
+            assert_eq!(name, expected_name);
+            assert_eq!(contents, expected_contents);
+        }
+        // Nothing is large enough to need zip64
+        assert!(!zip
+            .windows(4)
+            .any(|bytes| bytes == ZIP64_END_OF_CENTRAL_DIRECTORY.to_le_bytes()));
+    }
+
+    #[test]
+    fn zip64_fields() {
+        let zip = write_zip(0);
+        let files = read_zip(&zip);
+        assert_eq!(files.len(), FILES.len());
+
+        // The local header of the first file has its sizes in the zip64 extra field
+        let name_length = u16_at(&zip, 26) as usize;
+        assert_eq!(u16_at(&zip, 4), 45);
+        assert_eq!(u32_at(&zip, 18), u32::MAX);
+        assert_eq!(u32_at(&zip, 22), u32::MAX);
+        assert_eq!(u16_at(&zip, 28), 20);
+        let extra = 30 + name_length;
+        assert_eq!(u16_at(&zip, extra), ZIP64_EXTRA);
+        assert_eq!(u16_at(&zip, extra + 2), 16);
+        assert_eq!(u64_at(&zip, extra + 4), FILES[0].1.len() as u64);
+        let compressed_size = u64_at(&zip, extra + 12);
+        let (_, central_compressed_size, offset) = central_values(&zip, read_directory_start(&zip));
+        assert_eq!(compressed_size, central_compressed_size);
+        // The first file is at offset 0, which fits without the extra field
+        assert_eq!(offset, 0);
+    }
+
+    fn read_directory_start(zip: &[u8]) -> usize {
+        u32_at(zip, zip.len() - 22 + 16) as usize
+    }
+}
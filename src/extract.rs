@@ -1,53 +1,341 @@
 use flate2::read::GzDecoder;
+use quick_xml::{
+    events::{BytesEnd, Event},
+    name::QName,
+    Reader, Writer,
+};
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-/// Extracts the *xml* contents out of the *als* file
-pub fn extract(als_path: String) -> Result<String, Box<dyn std::error::Error>> {
-    let file = File::open(als_path)?;
-    let mut decoder = GzDecoder::new(file);
-    let mut xml_contents = String::new();
-    decoder.read_to_string(&mut xml_contents)?;
+/// Elements which no `parse` module reads, plugin states, parameters, automation and notes
+/// make up most of a set's *xml* so leaving them out shrinks it to a fraction
+const UNUSED_ELEMENTS: [&[u8]; 13] = [
+    b"AutomationEnvelopes",
+    b"AutomationTarget",
+    b"Branches",
+    b"Buffer",
+    b"Envelopes",
+    b"KeyTracks",
+    b"LastUserRange",
+    b"LockEnvelope",
+    b"MidiControllerRange",
+    b"ModulationTarget",
+    b"ParameterList",
+    b"Preset",
+    b"WarpMarkers",
+];
 
-    return Ok(xml_contents);
+/// Elements which are left out entirely when a damaged file ends within them,
+/// as `parse` expects every track and clip to be complete
+const WHOLE_ELEMENTS: [&[u8]; 9] = [
+    b"AudioClip",
+    b"AudioTrack",
+    b"GroupTrack",
+    b"MainTrack",
+    b"MasterTrack",
+    b"MidiClip",
+    b"MidiTrack",
+    b"PreHearTrack",
+    b"ReturnTrack",
+];
+
+/// Sets are *gzip* compressed *xml*, some tools save them uncompressed
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most bytes of *xml* a single set may decompress to, see `set_size_limit`
+static SIZE_LIMIT: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Limits how many bytes of *xml* a set may decompress to,
+/// so a damaged or malicious file cannot fill up memory
+pub fn set_size_limit(bytes: u64) {
+    SIZE_LIMIT.store(bytes, Ordering::Relaxed);
+}
+
+/// The *xml* of a set along with whether the file was damaged,
+/// in which case the *xml* is only the part of it which could be salvaged
+pub struct Extracted {
+    pub xml: String,
+    pub salvaged: bool,
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(io::Error),
+    /// Neither *gzip* compressed nor plain *xml*
+    UnknownFormat,
+    /// Decompressed to more than the size limit
+    TooLarge {
+        limit: u64,
+    },
+    /// The *xml* is not valid UTF-8 from this byte on
+    NotUtf8 {
+        position: u64,
+    },
+    Xml(quick_xml::Error),
+    /// Ends early or is corrupted part way, only returned where nothing is salvaged
+    Damaged,
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractError::Io(e) => write!(f, "{}", e),
+            ExtractError::UnknownFormat => {
+                write!(
+                    f,
+                    "Not an ableton set, it is neither gzip compressed nor xml"
+                )
+            }
+            ExtractError::TooLarge { limit } => write!(
+                f,
+                "Decompresses to more than the limit of {} MB, \
+                 raise max_decompressed_mb in settings.yaml to load it",
+                limit / 1_000_000
+            ),
+            ExtractError::NotUtf8 { position } => write!(
+                f,
+                "Contains text which is not valid UTF-8 at byte {} of its xml",
+                position
+            ),
+            ExtractError::Xml(e) => write!(f, "Invalid xml: {}", e),
+            ExtractError::Damaged => write!(f, "The file is damaged or truncated"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<io::Error> for ExtractError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::FileTooLarge => ExtractError::TooLarge {
+                limit: SIZE_LIMIT.load(Ordering::Relaxed),
+            },
+            _ => ExtractError::Io(e),
+        }
+    }
+}
+
+impl From<quick_xml::Error> for ExtractError {
+    fn from(e: quick_xml::Error) -> Self {
+        match e {
+            // Errors of the underlying reader such as the size limit are shared by quick-xml
+            quick_xml::Error::Io(e) => io::Error::new(e.kind(), e.to_string()).into(),
+            e => ExtractError::Xml(e),
+        }
+    }
+}
+
+/// Extracts the *xml* contents out of the *als* file,
+/// closing the elements left open when a damaged file could only be decompressed in part
+pub fn extract(als_path: String) -> Result<Extracted, ExtractError> {
+    let mut reader = open(&als_path)?;
+    let mut bytes = Vec::new();
+
+    let truncated = match reader.read_to_end(&mut bytes) {
+        Ok(_) => false,
+        Err(e) if is_damaged(&e) && !bytes.is_empty() => true,
+        Err(e) => return Err(e.into()),
+    };
+
+    if truncated {
+        return prune(&bytes[..], &[]).map(|extracted| Extracted {
+            salvaged: true,
+            ..extracted
+        });
+    }
+
+    let xml = String::from_utf8(bytes).map_err(|e| ExtractError::NotUtf8 {
+        position: e.utf8_error().valid_up_to() as u64,
+    })?;
+    Ok(Extracted {
+        xml,
+        salvaged: false,
+    })
+}
+
+/// Extracts the *xml* contents out of the *als* file without salvaging anything, for sets
+/// which are written back or have to be read completely. A damaged file is an error
+/// rather than giving the part of it which could be decompressed
+pub fn extract_strict(als_path: &Path) -> Result<String, ExtractError> {
+    let mut reader = open(&als_path.to_string_lossy())?;
+    let mut bytes = Vec::new();
+
+    match reader.read_to_end(&mut bytes) {
+        Ok(_) => {}
+        Err(e) if is_damaged(&e) => return Err(ExtractError::Damaged),
+        Err(e) => return Err(e.into()),
+    }
+
+    String::from_utf8(bytes).map_err(|e| ExtractError::NotUtf8 {
+        position: e.utf8_error().valid_up_to() as u64,
+    })
+}
+
+/// Extracts the *xml* contents out of the *als* file while it is decompressed, leaving out
+/// `UNUSED_ELEMENTS`. The whole *xml* is never held in memory, only the pruned remainder which
+/// is still parsed into a full document. Parsing the result gives the same `AlsData`
+/// as parsing `extract`'s *xml*, which is tested on the example set
+pub fn extract_pruned(als_path: &str) -> Result<Extracted, ExtractError> {
+    prune(open(als_path)?, &UNUSED_ELEMENTS)
 }
 
-// use flate2::read::GzDecoder;
-// use std::fs::File;
-// use std::io::{self, Read};
+/// Opens an *als* file for reading its *xml*, decompressing it if it is *gzip* compressed
+fn open(als_path: &str) -> Result<Box<dyn BufRead>, ExtractError> {
+    let mut file = BufReader::new(File::open(als_path)?);
+    let header = file.fill_buf()?;
+
+    let xml: Box<dyn Read> = if header.starts_with(&GZIP_MAGIC) {
+        Box::new(GzDecoder::new(file))
+    } else if is_xml(header) {
+        Box::new(file)
+    } else {
+        return Err(ExtractError::UnknownFormat);
+    };
 
-// /// Extracts the *xml* contents out of the *als* file
-// pub fn extract(als_path: String) -> Result<String, Box<dyn std::error::Error>> {
-//     let mut file = File::open(als_path)?;
-//     let mut xml_contents = String::new();
+    Ok(Box::new(BufReader::new(Limited {
+        inner: xml,
+        remaining: SIZE_LIMIT.load(Ordering::Relaxed),
+    })))
+}
 
-//     if is_gzip_file(&file)? {
-//         println!("HELLOW");
-//         let mut decoder = GzDecoder::new(file);
-//         decoder.read_to_string(&mut xml_contents)?;
-//     } else {
-//         file.read_to_string(&mut xml_contents);
-//     }
+/// Copies the *xml* events of `reader` except for `unused` elements. When the *xml* ends early
+/// the elements which are still open are closed so what was read can still be parsed,
+/// leaving out the incomplete track or clip it ended in
+fn prune(reader: impl BufRead, unused: &[&[u8]]) -> Result<Extracted, ExtractError> {
+    let mut reader = Reader::from_reader(reader);
+    let mut writer = Writer::new(Vec::new());
 
-//     return Ok(xml_contents);
-// }
+    let is_unused = |name: QName| unused.contains(&name.as_ref());
 
-// // I didnt write this...
-// fn is_gzip_file(mut f: &File) -> io::Result<bool> {
-//     let mut header = [0u8; 10]; // Gzip header is at least 10 bytes
+    // Names of the elements which are open along with where they start in the output
+    let mut open_elements: Vec<(Vec<u8>, usize)> = Vec::new();
+    let mut buf = Vec::new();
+    let mut skipped = Vec::new();
+    let mut salvaged = false;
+    loop {
+        let position = reader.buffer_position() as u64;
+        let event = match reader.read_event_into(&mut buf) {
+            Ok(event) => event,
+            Err(e) if ends_early(&e) && !open_elements.is_empty() => {
+                salvaged = true;
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if std::str::from_utf8(&event).is_err() {
+            return Err(ExtractError::NotUtf8 { position });
+        }
 
-//     // Read the first 10 bytes
-//     f.read_exact(&mut header)?;
+        match event {
+            Event::Start(element) if is_unused(element.name()) => {
+                match reader.read_to_end_into(element.name(), &mut skipped) {
+                    Ok(_) => skipped.clear(),
+                    Err(e) if ends_early(&e) => {
+                        salvaged = true;
+                        break;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            Event::Empty(element) if is_unused(element.name()) => {}
+            Event::Start(element) => {
+                open_elements.push((element.name().as_ref().to_vec(), writer.get_ref().len()));
+                writer.write_event(Event::Start(element))?;
+            }
+            Event::End(element) => {
+                open_elements.pop();
+                writer.write_event(Event::End(element))?;
+            }
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+        buf.clear();
+    }
 
-//     // Check ID bytes
-//     if header[0] != 0x1F || header[1] != 0x8B {
-//         return Ok(false);
-//     }
+    if let Some(incomplete) = open_elements
+        .iter()
+        .position(|(name, _)| WHOLE_ELEMENTS.contains(&name.as_slice()))
+    {
+        writer.get_mut().truncate(open_elements[incomplete].1);
+        open_elements.truncate(incomplete);
+    }
+    while let Some((name, _)) = open_elements.pop() {
+        let name = String::from_utf8_lossy(&name).to_string();
+        writer.write_event(Event::End(BytesEnd::new(name)))?;
+    }
 
-//     // Check compression method (should be 8 for DEFLATE)
-//     if header[2] != 8 {
-//         return Ok(false);
-//     }
+    let xml = String::from_utf8(writer.into_inner()).map_err(|e| ExtractError::NotUtf8 {
+        position: e.utf8_error().valid_up_to() as u64,
+    })?;
+    Ok(Extracted { xml, salvaged })
+}
+
+/// Whether decompressing failed part way, as it does for truncated or corrupted files
+fn is_damaged(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData
+    )
+}
+
+/// Whether the *xml* stopped part way, either while decompressing it or within an element
+fn ends_early(e: &quick_xml::Error) -> bool {
+    match e {
+        quick_xml::Error::Io(e) => is_damaged(e),
+        quick_xml::Error::UnexpectedEof(_) => true,
+        _ => false,
+    }
+}
 
-//     Ok(true)
-// }
+/// Whether the start of a file looks like *xml*, allowing for a byte order mark and whitespace
+fn is_xml(header: &[u8]) -> bool {
+    let header = header.strip_prefix(b"\xef\xbb\xbf").unwrap_or(header);
+    header
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|&byte| byte == b'<')
+}
+
+/// Fails reading with `FileTooLarge` once more than `remaining` bytes were read
+struct Limited<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.remaining = self
+            .remaining
+            .checked_sub(read as u64)
+            .ok_or(io::ErrorKind::FileTooLarge)?;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::als::AlsData;
+
+    const EXAMPLE_SET: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/Mr. Bill - Pastel Project/Mr. Bill - Pastel.als"
+    );
+
+    #[test]
+    fn pruning_parses_the_same() {
+        let parse = |xml: String| {
+            serde_yaml::to_string(&AlsData::parse("Pastel".to_string(), xml)).unwrap()
+        };
+        let full = extract(EXAMPLE_SET.to_string()).unwrap().xml;
+        let pruned = extract_pruned(EXAMPLE_SET).unwrap().xml;
+
+        assert!(pruned.len() < full.len() / 2);
+        assert_eq!(parse(pruned), parse(full));
+    }
+}
@@ -1,15 +1,29 @@
-use crate::{gui::tabs::TabType, parse::als::Project};
+use crate::{
+    cache::{self, THUMBNAIL_SIZE},
+    colors,
+    diff::ClipIndex,
+    gui::{search::highlighted, tabs::TabType},
+    history::BackupTimestamp,
+    loader::Loader,
+    message::Message,
+    parse::als::{AlsData, Project},
+    progress::ProgressReport,
+    search::{self, Search},
+    settings::Settings,
+    sort::{Sort, SortColumn},
+    stats::LibraryStats,
+};
 use eframe::egui::{
     self, widgets::Spinner, Align, IconData, SelectableLabel, TextStyle, TextureHandle, Vec2,
 };
 use egui_dock::DockState;
-use egui_extras::install_image_loaders;
+use egui_extras::{install_image_loaders, Column, TableBuilder};
 use image;
 use std::{
-    fs::File,
-    io::{self, BufRead},
-    path::Path,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     sync::mpsc,
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 
@@ -20,11 +34,77 @@ pub enum GuiState {
     Error,
 }
 
+pub(super) const SALVAGED_WARNING: &str =
+    "This set is damaged, only the part of it which could be salvaged was loaded";
+
+/// The differences between the compared and the selected set,
+/// worked out again only when other sets are shown or the sets change
+pub(super) struct ShownDiff {
+    /// The selected set, compared set, backup and whether it is compared it was worked out for
+    pub(super) shown: ShownSets,
+    pub(super) summary: String,
+    pub(super) changes: Vec<String>,
+    /// Clips and tracks of the selected set which were changed or added
+    pub(super) changed_new: (Vec<ClipIndex>, Vec<usize>),
+    /// Clips and tracks of the compared set which were changed or removed
+    pub(super) changed_old: (Vec<ClipIndex>, Vec<usize>),
+}
+
+pub(super) type ShownSets = (
+    Option<(usize, usize)>,
+    Option<(usize, usize)>,
+    Option<usize>,
+    bool,
+);
+
+/// The rows of the als file list, worked out again only when the search, sort or sets change
+struct AlsRows {
+    search: Search,
+    sort: Sort,
+    /// Searching by modification date depends on the time, so the rows are also
+    /// worked out again every minute
+    minute: u64,
+    /// The sets passing the search in sorted order
+    rows: Vec<(usize, usize)>,
+    /// The characters of each shown set's name which were matched by the search
+    matches: HashMap<(usize, usize), Vec<usize>>,
+    /// Sets of projects which are still loading, listed after the loaded ones
+    pending: Vec<(usize, String)>,
+    /// How many sets there are before searching
+    total: usize,
+}
+
 pub struct Gui {
-    receiver: mpsc::Receiver<Result<Vec<Project>, String>>,
+    receiver: mpsc::Receiver<Message>,
+    /// Loads projects in the background, shared to prioritise or cancel loading
+    loader: Loader,
     state: GuiState,
     pub projects: Option<Vec<Project>>,
+    /// Names of the sets of projects which are still loading
+    pending_sets: HashMap<String, Vec<String>>,
+    /// Project and name of a set clicked while it was loading, selected once it is loaded
+    awaited_set: Option<(String, String)>,
     pub selected_project_als: Option<(usize, usize)>,
+    /// The set shown below the selected set in the viewer when comparing
+    pub compare_project_als: Option<(usize, usize)>,
+    /// Index into the selected project's backups of the version shown instead of the set
+    pub history_version: Option<usize>,
+    pub compare_with_history: bool,
+    /// Track and clip index of the clip shown in the inspector
+    pub selected_clip: Option<(usize, usize)>,
+    /// Index of the track shown in the inspector, also set when one of its clips is selected
+    pub selected_track: Option<usize>,
+    /// Ids of the tracks whose take lanes are shown below them in the preview
+    pub expanded_tracks: HashSet<i32>,
+    /// Narrows down the sets shown in the als file list
+    pub search: Search,
+    als_rows: Option<AlsRows>,
+    pub(super) shown_diff: Option<ShownDiff>,
+    pub(super) settings: Settings,
+    /// Shown in the statistics tab, worked out again in the background when projects change
+    pub(super) statistics: Option<LibraryStats>,
+    pub(super) statistics_job: Option<JoinHandle<LibraryStats>>,
+    pub(super) statistics_outdated: bool,
     pub dock_state: DockState<TabType>,
 
     pub preview_x_scale: f32,
@@ -33,32 +113,54 @@ pub struct Gui {
 
     pub colors: [String; 70],
     icon_path: String,
+    /// Overview images of the sets in the als file list, `None` when one could not be loaded
+    thumbnails: HashMap<PathBuf, Option<TextureHandle>>,
 
     info: String,
+    /// How far loading or reloading sets has come
+    progress: ProgressReport,
+    /// The outcome of the last action, such as an export, shown in the info bar
+    pub status: Option<String>,
     pub error_msg: String,
     frame_time: Duration,
 }
 
 impl Gui {
-    pub fn new(receiver: mpsc::Receiver<Result<Vec<Project>, String>>) -> Self {
+    pub fn new(receiver: mpsc::Receiver<Message>, loader: Loader) -> Self {
         Self {
             receiver,
+            loader,
             state: GuiState::Loading,
             projects: None,
+            pending_sets: HashMap::new(),
+            awaited_set: None,
             selected_project_als: None,
+            compare_project_als: None,
+            history_version: None,
+            compare_with_history: false,
+            selected_clip: None,
+            selected_track: None,
+            expanded_tracks: HashSet::new(),
+            search: Search::default(),
+            als_rows: None,
+            shown_diff: None,
+            settings: Settings::load(),
+            statistics: None,
+            statistics_job: None,
+            statistics_outdated: true,
             dock_state: Gui::default_tab_layout(),
 
             preview_x_pos: 0.,
             preview_x_scale: 3.,
             preview_y_scale: 13.,
 
-            colors: match load_colors() {
-                Ok(colors) => colors,
-                Err(_) => init_colors(), // On this arm colors will just be errored and purple
-            },
+            colors: colors::palette(), // Colors will just be errored and purple if loading fails
             icon_path: String::from("assets/palsa/icon.png"),
+            thumbnails: HashMap::new(),
 
             info: String::new(),
+            progress: ProgressReport::default(),
+            status: None,
             error_msg: "THERE WAS AN ERROR BUT HOW???".to_string(),
             frame_time: Duration::new(0, 0),
         }
@@ -101,6 +203,30 @@ impl Gui {
             // Left to right side ui elements
             ui.with_layout(egui::Layout::left_to_right(Align::TOP), |ui| {
                 self.gen_info(ui);
+                if matches!(self.state, GuiState::Loaded)
+                    && !self.progress.is_finished()
+                    && !self.loader.is_cancelled()
+                {
+                    ui.separator();
+                    ui.add(
+                        egui::ProgressBar::new(self.progress.fraction())
+                            .desired_width(150.)
+                            .text(format!(
+                                "{}/{}",
+                                self.progress.done(),
+                                self.progress.discovered
+                            )),
+                    )
+                    .on_hover_text(self.progress.to_string());
+                    if ui.button("Cancel").clicked() {
+                        self.loader.cancel();
+                        self.status = Some("Cancelled loading".to_string());
+                    }
+                }
+                if let Some(ref status) = self.status {
+                    ui.separator();
+                    ui.label(egui::RichText::new(status).size(15.));
+                }
 
                 // Displays frame time of the application
                 ui.with_layout(egui::Layout::right_to_left(Align::TOP), |ui| {
@@ -124,42 +250,481 @@ impl Gui {
         }
     }
 
-    /// Lists the als files and lets the user select one
-    pub fn als_panel(
-        &mut self,
-        ui: &mut egui::Ui,
-        projects: &Vec<Project>,
-    ) -> Option<(usize, usize)> {
-        egui::ScrollArea::vertical()
+    /// Lists the als files in a sortable table and lets the user select one
+    pub fn als_panel(&mut self, ui: &mut egui::Ui, projects: &[Project]) -> Option<(usize, usize)> {
+        let now = search::now();
+        let outdated = self.als_rows.as_ref().is_none_or(|cached| {
+            cached.search != self.search
+                || cached.sort != self.settings.sort
+                || cached.minute != now / 60
+        });
+        let als_rows = match self.als_rows.take() {
+            Some(als_rows) if !outdated => als_rows,
+            _ => self.als_rows(projects, now),
+        };
+        let AlsRows {
+            ref rows,
+            ref matches,
+            ref pending,
+            total,
+            ..
+        } = als_rows;
+
+        self.search_bar(ui, rows.len(), total);
+
+        let mut sort = self.settings.sort;
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
             .auto_shrink([false; 2])
-            .show(ui, |ui| {
-                for (project_index, project) in projects.iter().enumerate() {
-                    ui.collapsing(&project.name, |ui| {
-                        if let Some(ref als_data) = project.als_data {
-                            for (als_index, als) in als_data.iter().enumerate() {
-                                let is_selected =
-                                    self.selected_project_als == Some((project_index, als_index));
-                                let als_response =
-                                    ui.add(SelectableLabel::new(is_selected, &als.name));
-
-                                if als_response.clicked() {
-                                    if is_selected {
-                                        self.selected_project_als = None;
-                                    } else {
-                                        self.selected_project_als =
-                                            Some((project_index, als_index));
-                                    }
-                                }
-                            }
+            .cell_layout(egui::Layout::left_to_right(Align::Center))
+            .column(Column::auto().at_least(THUMBNAIL_SIZE[0] as f32 + 60.))
+            .columns(Column::auto(), SortColumn::ALL.len() - 1)
+            .header(20., |mut header| {
+                for column in SortColumn::ALL {
+                    header.col(|ui| {
+                        let label = match (sort.column == column, sort.descending) {
+                            (true, true) => format!("{} ⏷", column.label()),
+                            (true, false) => format!("{} ⏶", column.label()),
+                            _ => column.label().to_string(),
+                        };
+                        if ui
+                            .add(SelectableLabel::new(sort.column == column, label))
+                            .clicked()
+                        {
+                            sort.toggle(column);
                         }
                     });
                 }
-                ui.add_space(50.0);
+            })
+            .body(|body| {
+                let row_count = rows.len() + pending.len();
+                body.rows(THUMBNAIL_SIZE[1] as f32 + 4., row_count, |mut row| {
+                    if let Some((project_index, name)) =
+                        row.index().checked_sub(rows.len()).map(|i| &pending[i])
+                    {
+                        let project = &projects[*project_index];
+                        row.col(|ui| self.pending_name_cell(ui, project, name));
+                        row.col(|ui| {
+                            ui.label(&project.name);
+                        });
+                        return;
+                    }
+
+                    let (project_index, als_index) = rows[row.index()];
+                    let project = &projects[project_index];
+                    let als = &project.als_data.as_ref().unwrap()[als_index];
+
+                    row.col(|ui| {
+                        self.als_name_cell(ui, project, als, (project_index, als_index), matches)
+                    });
+                    row.col(|ui| {
+                        ui.label(&project.name);
+                    });
+                    row.col(|ui| {
+                        ui.label(BackupTimestamp::from_unix_local(als.modified).to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.2}", als.tempo));
+                    });
+                    row.col(|ui| {
+                        ui.label(als.length_in_bars().to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(als.track_count().to_string());
+                    });
+                    row.col(|ui| {
+                        let clips: usize = als.tracks.iter().map(|track| track.clips.len()).sum();
+                        ui.label(clips.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format_size(als.file_size));
+                    });
+                    row.col(|ui| {
+                        ui.label(als.version.to_string());
+                    });
+                });
             });
 
+        self.als_rows = Some(als_rows);
+        if sort != self.settings.sort {
+            self.settings.sort = sort;
+            if let Err(e) = self.settings.save() {
+                self.status = Some(e);
+            }
+        }
+
         self.selected_project_als
     }
 
+    /// Searches and sorts the sets for the als file list
+    fn als_rows(&self, projects: &[Project], now: u64) -> AlsRows {
+        let mut matches = HashMap::new();
+        let mut total = 0;
+        for (project_index, project) in projects.iter().enumerate() {
+            for (als_index, als) in project.als_data.iter().flatten().enumerate() {
+                total += 1;
+                if let Some(matched) = self.search.matches(als, now) {
+                    matches.insert((project_index, als_index), matched);
+                }
+            }
+        }
+
+        let mut rows: Vec<(usize, usize)> = matches.keys().copied().collect();
+        self.settings.sort.sort(projects, &mut rows);
+
+        let pending: Vec<(usize, String)> = projects
+            .iter()
+            .enumerate()
+            .flat_map(|(project_index, project)| {
+                self.pending_sets
+                    .get(&project.name)
+                    .into_iter()
+                    .flatten()
+                    .filter(|name| search::fuzzy_match(&self.search.query, name).is_some())
+                    .map(move |name| (project_index, name.clone()))
+            })
+            .collect();
+
+        AlsRows {
+            search: self.search.clone(),
+            sort: self.settings.sort,
+            minute: now / 60,
+            rows,
+            matches,
+            pending,
+            total,
+        }
+    }
+
+    /// A set of a project which is still loading, clicking it loads its project next
+    /// and selects the set once it is loaded
+    fn pending_name_cell(&mut self, ui: &mut egui::Ui, project: &Project, name: &str) {
+        if self.loader.is_cancelled() {
+            ui.add_enabled(false, egui::Label::new(format!("{} (not loaded)", name)));
+            return;
+        }
+
+        ui.add(Spinner::new());
+        let is_awaited =
+            self.awaited_set.as_ref() == Some(&(project.name.clone(), name.to_string()));
+        if ui
+            .add(SelectableLabel::new(
+                is_awaited,
+                egui::RichText::new(name).weak(),
+            ))
+            .on_hover_text("Loading, click to load this set next")
+            .clicked()
+        {
+            self.loader.prioritise(&project.name, name);
+            self.awaited_set = Some((project.name.clone(), name.to_string()));
+        }
+    }
+
+    /// The thumbnail and name of a set, clicking it selects the set
+    /// and right clicking it lets the set be compared with the selected one
+    fn als_name_cell(
+        &mut self,
+        ui: &mut egui::Ui,
+        project: &Project,
+        als: &AlsData,
+        index: (usize, usize),
+        matches: &HashMap<(usize, usize), Vec<usize>>,
+    ) {
+        let is_selected = self.selected_project_als == Some(index);
+        let is_compared = self.compare_project_als == Some(index);
+
+        if let Some(thumbnail) = self.thumbnail(ui.ctx(), &project.name, &als.name) {
+            ui.image(&thumbnail);
+        }
+        let als_response = ui.add(SelectableLabel::new(
+            is_selected || is_compared,
+            if is_compared {
+                egui::WidgetText::from(format!("{} (comparing)", als.name))
+            } else {
+                highlighted(ui, &als.name, &matches[&index]).into()
+            },
+        ));
+        if als.salvaged {
+            ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                .on_hover_text(SALVAGED_WARNING);
+        }
+
+        als_response.context_menu(|ui| {
+            if is_compared {
+                if ui.button("Stop comparing").clicked() {
+                    self.compare_project_als = None;
+                    ui.close_menu();
+                }
+            } else if self.selected_project_als.is_some()
+                && !is_selected
+                && ui.button("Compare with selected").clicked()
+            {
+                self.compare_project_als = Some(index);
+                ui.close_menu();
+            }
+        });
+
+        if als_response.clicked() {
+            self.selected_clip = None;
+            self.selected_track = None;
+            self.history_version = None;
+            if is_compared {
+                self.compare_project_als = None;
+            }
+            if is_selected {
+                self.selected_project_als = None;
+            } else {
+                self.selected_project_als = Some(index);
+            }
+        }
+    }
+
+    /// Loads the cached overview image of a set the first time it is shown
+    fn thumbnail(
+        &mut self,
+        ctx: &egui::Context,
+        project_name: &str,
+        als_name: &str,
+    ) -> Option<TextureHandle> {
+        let path = cache::thumbnail_path(&format!("cache/{}", project_name), als_name);
+        self.thumbnails
+            .entry(path)
+            .or_insert_with_key(|path| {
+                let image = image::open(path).ok()?.to_rgba8();
+                let dimensions = image.dimensions();
+                Some(ctx.load_texture(
+                    path.to_string_lossy(),
+                    egui::ColorImage::from_rgba_unmultiplied(
+                        [dimensions.0 as _, dimensions.1 as _],
+                        &image.into_raw(),
+                    ),
+                    egui::TextureOptions::default(),
+                ))
+            })
+            .clone()
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        if !matches!(message, Message::Progress(_) | Message::Error(_)) {
+            // Every other message changes which sets are listed
+            self.als_rows = None;
+            self.shown_diff = None;
+        }
+        if matches!(
+            message,
+            Message::ProjectLoaded(_) | Message::SetChanged { .. } | Message::SetRemoved { .. }
+        ) {
+            self.statistics_outdated = true;
+        }
+
+        match message {
+            Message::Progress(report) => self.progress = report,
+            Message::Discovered(discovered) => {
+                self.projects = Some(
+                    discovered
+                        .iter()
+                        .map(|(name, _)| Project {
+                            name: name.clone(),
+                            als_data: None,
+                            backups: Vec::new(),
+                        })
+                        .collect(),
+                );
+                self.pending_sets = discovered.into_iter().collect();
+                self.state = GuiState::Loaded;
+            }
+            Message::ProjectLoaded(project) => self.project_loaded(project),
+            Message::SetChanged {
+                project,
+                backup,
+                als_data,
+            } => {
+                self.status = Some(format!("Reloaded {}", als_data.name));
+                self.set_changed(project, backup, als_data);
+            }
+            Message::SetRemoved {
+                project,
+                backup,
+                name,
+            } => {
+                self.status = Some(format!("Removed {}", name));
+                self.set_removed(&project, backup, &name);
+            }
+            Message::Error(error) => match self.state {
+                GuiState::Loading => {
+                    self.error_msg = error;
+                    self.state = GuiState::Error;
+                }
+                _ => self.status = Some(error),
+            },
+        }
+    }
+
+    /// Replaces the placeholder of a project which finished loading,
+    /// selecting the set which was clicked while it was loading
+    fn project_loaded(&mut self, project: Project) {
+        let Some(ref mut projects) = self.projects else {
+            return;
+        };
+        // A set loaded ahead of the rest of its project leaves the others pending
+        let loaded = |name: &String| {
+            project
+                .als_data
+                .iter()
+                .flatten()
+                .any(|als| als.name == *name)
+        };
+        if let Some(pending) = self.pending_sets.get_mut(&project.name) {
+            pending.retain(|name| !loaded(name));
+            if pending.is_empty() {
+                self.pending_sets.remove(&project.name);
+            }
+        }
+
+        let project_index = match projects.iter().position(|p| p.name == project.name) {
+            Some(index) => index,
+            None => {
+                projects.push(Project {
+                    name: project.name.clone(),
+                    als_data: None,
+                    backups: Vec::new(),
+                });
+                projects.len() - 1
+            }
+        };
+
+        // The sets already shown may be in another order once the rest of the project is loaded
+        let reindex = |selected: Option<(usize, usize)>| match selected {
+            Some((selected_project, als_index)) if selected_project == project_index => {
+                let name = &projects[project_index]
+                    .als_data
+                    .as_ref()?
+                    .get(als_index)?
+                    .name;
+                let als_index = project
+                    .als_data
+                    .iter()
+                    .flatten()
+                    .position(|als| als.name == *name)?;
+                Some((project_index, als_index))
+            }
+            selected => selected,
+        };
+        self.selected_project_als = reindex(self.selected_project_als);
+        self.compare_project_als = reindex(self.compare_project_als);
+        // So may its backups, which may also be fewer than before
+        if self.selected_project_als.map(|(p, _)| p) == Some(project_index) {
+            self.history_version = self.history_version.and_then(|backup_index| {
+                let name = &projects[project_index].backups.get(backup_index)?.name;
+                project
+                    .backups
+                    .iter()
+                    .position(|backup| backup.name == *name)
+            });
+        }
+
+        if let Some((ref project_name, ref set_name)) = self.awaited_set {
+            if *project_name == project.name {
+                let als_index = project
+                    .als_data
+                    .iter()
+                    .flatten()
+                    .position(|als| als.name == *set_name);
+                if let Some(als_index) = als_index {
+                    self.selected_project_als = Some((project_index, als_index));
+                    self.selected_clip = None;
+                    self.selected_track = None;
+                    self.history_version = None;
+                }
+                self.awaited_set = None;
+            }
+        }
+
+        projects[project_index] = project;
+    }
+
+    /// Replaces a set which was saved again or adds a new one, new sets and projects are added
+    /// at the end so the indices of the selected and compared sets stay the same
+    fn set_changed(&mut self, project_name: String, backup: bool, als_data: AlsData) {
+        let Some(ref mut projects) = self.projects else {
+            return;
+        };
+
+        let project_index = match projects.iter().position(|p| p.name == project_name) {
+            // Projects which were not loaded show the changed set once they are
+            Some(index) if projects[index].als_data.is_none() => return,
+            Some(index) => index,
+            None => {
+                projects.push(Project {
+                    name: project_name.clone(),
+                    als_data: Some(Vec::new()),
+                    backups: Vec::new(),
+                });
+                projects.len() - 1
+            }
+        };
+
+        let cache_dir = if backup {
+            format!("cache/{}/Backup", project_name)
+        } else {
+            format!("cache/{}", project_name)
+        };
+        self.thumbnails
+            .remove(&cache::thumbnail_path(&cache_dir, &als_data.name));
+
+        let project = &mut projects[project_index];
+        let sets = if backup {
+            &mut project.backups
+        } else {
+            project.als_data.get_or_insert_with(Vec::new)
+        };
+        match sets.iter_mut().find(|set| set.name == als_data.name) {
+            Some(set) => *set = als_data,
+            None => sets.push(als_data),
+        }
+    }
+
+    /// Removes a deleted set, shifting or clearing the selection which pointed past or at it
+    fn set_removed(&mut self, project_name: &str, backup: bool, name: &str) {
+        let Some(ref mut projects) = self.projects else {
+            return;
+        };
+        let Some(project_index) = projects.iter().position(|p| p.name == project_name) else {
+            return;
+        };
+        let project = &mut projects[project_index];
+
+        if backup {
+            project.backups.retain(|set| set.name != name);
+            // Backup indices have shifted so the timeline starts over at the current version
+            if self.selected_project_als.map(|(p, _)| p) == Some(project_index) {
+                self.history_version = None;
+            }
+            return;
+        }
+
+        let sets = project.als_data.get_or_insert_with(Vec::new);
+        let Some(als_index) = sets.iter().position(|set| set.name == name) else {
+            return;
+        };
+        sets.remove(als_index);
+
+        let shift = |index: Option<(usize, usize)>| match index {
+            Some((p, a)) if p == project_index && a == als_index => None,
+            Some((p, a)) if p == project_index && a > als_index => Some((p, a - 1)),
+            index => index,
+        };
+        let selected = shift(self.selected_project_als);
+        if selected.is_none() {
+            self.selected_clip = None;
+            self.selected_track = None;
+            self.history_version = None;
+        }
+        self.selected_project_als = selected;
+        self.compare_project_als = shift(self.compare_project_als);
+    }
+
     /// Displays a spinner when loading
     fn handle_loading(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -173,15 +738,25 @@ impl Gui {
                 );
 
                 ui.add_space(10.);
-                ui.vertical_centered(|ui| {
-                    ui.add(Spinner::new().size(20.));
-                });
+                if self.progress.discovered == 0 {
+                    ui.vertical_centered(|ui| {
+                        ui.add(Spinner::new().size(20.));
+                    });
+                } else {
+                    ui.add(
+                        egui::ProgressBar::new(self.progress.fraction())
+                            .desired_width(300.)
+                            .show_percentage(),
+                    );
+                    ui.label(self.progress.to_string());
+                }
             });
         });
     }
 
     /// Displays the main interface with `tabs`
     fn handle_loaded(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.menu_bar(ctx);
         egui::CentralPanel::default().show(ctx, |_ui| {
             self.tabs(ctx, frame);
 
@@ -200,7 +775,7 @@ impl Gui {
             ui.with_layout(
                 egui::Layout::centered_and_justified(egui::Direction::TopDown),
                 |ui| {
-                    ui.label(egui::RichText::new(format!("{}", self.error_msg)).size(50.));
+                    ui.label(egui::RichText::new(&self.error_msg).size(50.));
                 },
             );
         });
@@ -213,7 +788,7 @@ impl Gui {
             let dimensions = image.dimensions();
             let pixels = image.into_raw();
 
-            let texture = ctx.load_texture(
+            ctx.load_texture(
                 "icon",
                 egui::ColorImage::from_rgba_unmultiplied(
                     [dimensions.0 as _, dimensions.1 as _],
@@ -222,9 +797,7 @@ impl Gui {
                 egui::TextureOptions {
                     ..Default::default()
                 },
-            );
-
-            texture
+            )
         } else {
             panic!("Icon was not found at {:?}", &self.icon_path);
         }
@@ -233,20 +806,11 @@ impl Gui {
 
 impl eframe::App for Gui {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        install_image_loaders(&ctx);
+        install_image_loaders(ctx);
         let frame_start = Instant::now();
 
-        if let Ok(received) = self.receiver.try_recv() {
-            match received {
-                Ok(als_data) => {
-                    self.projects = Some(als_data);
-                    self.state = GuiState::Loaded;
-                }
-                Err(error) => {
-                    self.error_msg = error;
-                    self.state = GuiState::Error;
-                }
-            }
+        while let Ok(message) = self.receiver.try_recv() {
+            self.handle_message(message);
         }
 
         match self.state {
@@ -261,33 +825,18 @@ impl eframe::App for Gui {
     }
 }
 
-fn load_colors() -> Result<[String; 70], io::Error> {
-    let path = Path::new("assets/palsa/default-colors.txt");
-    let file = File::open(path)?;
-    let reader = io::BufReader::new(file);
-
-    let mut colors = init_colors();
-
-    for (i, line) in reader.lines().enumerate() {
-        if i >= 70 {
-            break; // Stop reading if more than 70 lines are encountered
-        }
-        colors[i] = line?;
+/// A file size in the largest unit it is at least one of
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000. && unit < UNITS.len() - 1 {
+        size /= 1000.;
+        unit += 1;
     }
-
-    // Check if we have exactly 70 colors
-    if colors.iter().any(|s| s.is_empty()) {
-        Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "File contains fewer than 70 lines",
-        ))
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
     } else {
-        Ok(colors)
+        format!("{:.1} {}", size, UNITS[unit])
     }
 }
-
-fn init_colors() -> [String; 70] {
-    // Initalizes an array of 70 purple hex values (Unloaded texture values)
-    // Cant use [String::from("800080"); 70] so im using this bellow to avoid the copy trait :(
-    return std::array::from_fn(|_| String::from("800080"));
-}
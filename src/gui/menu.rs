@@ -0,0 +1,71 @@
+use eframe::egui;
+use std::{fs, path::Path};
+
+use super::gui::Gui;
+use crate::export::{self, json::JsonExport, midi};
+use crate::parse::als::{AlsData, Project};
+
+impl Gui {
+    /// A bar along the top of the window with actions for the selected set
+    pub fn menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("Export", |ui| {
+                    let shown = self
+                        .projects
+                        .as_ref()
+                        .and_then(|projects| self.shown_als_data(projects))
+                        .map(|(als_data, _)| als_data);
+
+                    ui.add_enabled_ui(shown.is_some(), |ui| {
+                        if ui.button("Selected set as MIDI").clicked() {
+                            self.status = Some(match export_midi(shown.unwrap()) {
+                                Ok(path) => format!("Exported {}", path),
+                                Err(error) => error,
+                            });
+                            ui.close_menu();
+                        }
+                        if ui.button("Selected set as JSON").clicked() {
+                            self.status = Some(match export_json(shown.unwrap()) {
+                                Ok(path) => format!("Exported {}", path),
+                                Err(error) => error,
+                            });
+                            ui.close_menu();
+                        }
+                    });
+                });
+            });
+        });
+    }
+}
+
+fn export_midi(als_data: &AlsData) -> Result<String, String> {
+    if als_data.path.is_empty() {
+        return Err("The set's path is unknown".to_string());
+    }
+
+    let output = export::export_path(&als_data.name, "mid").map_err(|e| e.to_string())?;
+    let (bytes, salvaged) = midi::export_midi(Path::new(&als_data.path))?;
+    fs::write(&output, bytes)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    if salvaged {
+        return Ok(format!(
+            "{}, only from the part of the damaged set which could be salvaged",
+            output.display()
+        ));
+    }
+    Ok(output.display().to_string())
+}
+
+fn export_json(als_data: &AlsData) -> Result<String, String> {
+    let json = JsonExport::new(vec![Project {
+        name: String::new(),
+        als_data: Some(vec![als_data.clone()]),
+        backups: Vec::new(),
+    }])
+    .to_json()?;
+
+    let output = export::export_path(&als_data.name, "json").map_err(|e| e.to_string())?;
+    fs::write(&output, json).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    Ok(output.display().to_string())
+}
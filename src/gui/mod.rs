@@ -1,4 +1,10 @@
+#[allow(clippy::module_inception)]
 pub mod gui;
+mod menu;
 mod preview;
+mod routing;
+mod search;
+mod statistics;
 pub mod tabs;
+mod timeline;
 pub mod windows;
@@ -1,58 +1,511 @@
 use super::gui::Gui;
-use crate::parse::{als::AlsData, track::Track};
+use crate::diff::ClipIndex;
+use crate::parse::{
+    als::{AlsData, TimeSignature},
+    clip::Clip,
+    track::{TakeLane, Track, TrackKind, TrackSend},
+};
 
 use eframe::egui;
 
+/// Pixels between the stripes drawn over the clips of frozen tracks
+const HATCH_SPACING: f32 = 6.;
+
+/// Where and how a single set is drawn within the viewer
+struct PreviewArea {
+    painter: egui::Painter,
+    max_rect: egui::Rect,
+    id: egui::Id,
+    time_signature: TimeSignature,
+    /// Clips and tracks which differ from the set being compared against are highlighted
+    changed_clips: Vec<ClipIndex>,
+    changed_tracks: Vec<usize>,
+    /// Tracks which are muted or frozen themselves or by a group containing them
+    muted_tracks: Vec<usize>,
+    frozen_tracks: Vec<usize>,
+    /// Only the main preview's clips can be selected for the inspector
+    selectable: bool,
+}
+
 impl Gui {
-    pub fn visual_preview(&mut self, ui: &mut egui::Ui, selected_als_data: AlsData) {
+    /// Draws the selected set, when a set to compare with is given both are stacked vertically
+    /// and share the same position and zoom
+    pub fn visual_preview(
+        &mut self,
+        ui: &mut egui::Ui,
+        selected_als_data: &AlsData,
+        compare_als_data: Option<&AlsData>,
+    ) {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             let max_rect = ui.available_rect_before_wrap();
-            let painter = ui.painter_at(max_rect);
 
-            for (i, track) in selected_als_data.tracks.iter().enumerate() {
-                self.draw_clips(i, track, &painter, max_rect);
+            if let Some(compare_als_data) = compare_als_data {
+                let (top_rect, bottom_rect) = max_rect.split_top_bottom_at_fraction(0.5);
+                let bottom_rect = bottom_rect.shrink2(egui::vec2(0., 2.));
+                let (changed_new, changed_old) = self
+                    .shown_diff
+                    .as_ref()
+                    .map(|shown_diff| {
+                        (
+                            shown_diff.changed_new.clone(),
+                            shown_diff.changed_old.clone(),
+                        )
+                    })
+                    .unwrap_or_default();
+
+                self.draw_set(ui, selected_als_data, changed_new, top_rect, true);
+                ui.painter().hline(
+                    max_rect.x_range(),
+                    top_rect.max.y,
+                    ui.visuals().widgets.noninteractive.bg_stroke,
+                );
+                self.draw_set(ui, compare_als_data, changed_old, bottom_rect, false);
+            } else {
+                self.draw_set(ui, selected_als_data, (vec![], vec![]), max_rect, true);
             }
         });
     }
 
+    fn draw_set(
+        &mut self,
+        ui: &mut egui::Ui,
+        als_data: &AlsData,
+        (changed_clips, changed_tracks): (Vec<ClipIndex>, Vec<usize>),
+        max_rect: egui::Rect,
+        selectable: bool,
+    ) {
+        let area = PreviewArea {
+            painter: ui.painter_at(max_rect),
+            max_rect,
+            id: ui.id().with(("preview", selectable)),
+            time_signature: als_data.time_signature,
+            changed_clips,
+            changed_tracks,
+            muted_tracks: track_indexes(als_data, AlsData::is_muted),
+            frozen_tracks: track_indexes(als_data, AlsData::is_frozen),
+            selectable,
+        };
+
+        // Expanded tracks have a row below them for each of their take lanes
+        let mut row = 0;
+        let mut in_bottom_section = false;
+        for (i, track) in als_data.tracks.iter().enumerate() {
+            if track.kind == TrackKind::PreHear {
+                continue;
+            }
+            // Like in live the return tracks and the master track are kept apart below the others
+            if !track.kind.holds_clips() && !in_bottom_section {
+                in_bottom_section = true;
+                area.painter.hline(
+                    max_rect.x_range(),
+                    max_rect.min.y + (row as f32 + 0.5) * self.preview_y_scale,
+                    ui.visuals().widgets.noninteractive.bg_stroke,
+                );
+                row += 1;
+            }
+            self.draw_clips(ui, (i, row), track, &area);
+            row += 1;
+            if self.expanded_tracks.contains(&track.id) {
+                for lane in &track.take_lanes {
+                    self.draw_take_lane(ui, (i, row), track, lane, &area);
+                    row += 1;
+                }
+            }
+        }
+
+        if self.compare_project_als.is_some() {
+            area.painter.text(
+                max_rect.right_top(),
+                egui::Align2::RIGHT_TOP,
+                &als_data.name,
+                egui::FontId::proportional(14.),
+                ui.visuals().strong_text_color(),
+            );
+        }
+    }
+
     fn draw_clips(
         &mut self,
-        i: usize,
+        ui: &mut egui::Ui,
+        (i, row): (usize, usize),
         track: &Track,
-        painter: &egui::Painter,
-        max_rect: egui::Rect,
+        area: &PreviewArea,
     ) {
-        for clip in &track.clips {
-            let clip_rect = egui::Rect::from_x_y_ranges(
-                egui::Rangef::new(
-                    max_rect.min.x + self.preview_x_pos + clip.start * self.preview_x_scale,
-                    max_rect.min.x + self.preview_x_pos + clip.end * self.preview_x_scale,
-                ),
-                egui::Rangef::new(
-                    max_rect.min.y + (i as f32 * self.preview_y_scale),
-                    max_rect.min.y
-                        + (i as f32 * self.preview_y_scale)
-                        + self.preview_y_scale * 0.98,
-                ),
+        let painter = &area.painter;
+        let max_rect = area.max_rect;
+
+        for (clip_index, clip) in track.clips.iter().enumerate() {
+            let clip_rect = self.clip_rect(max_rect, row, clip.start, clip.end);
+            let muted = area.muted_tracks.contains(&i);
+            painter.rect_filled(clip_rect, 0.1, self.clip_color(track, clip, muted));
+            if area.frozen_tracks.contains(&i) {
+                hatch(painter, clip_rect);
+            }
+
+            // Clips outside of the preview can not be hovered or clicked
+            let visible_rect = clip_rect.intersect(max_rect);
+            if !visible_rect.is_positive() {
+                continue;
+            }
+
+            if area.changed_clips.contains(&(i, clip_index)) {
+                painter.rect_stroke(
+                    clip_rect,
+                    0.1,
+                    egui::Stroke::new(2., egui::Color32::from_rgb(255, 140, 0)),
+                );
+            }
+
+            let response = ui
+                .interact(
+                    visible_rect,
+                    area.id.with(("clip", i, clip_index)),
+                    egui::Sense::click(),
+                )
+                .on_hover_ui(|ui| clip_details(ui, clip, area.time_signature));
+
+            if area.selectable && response.clicked() {
+                self.selected_clip = Some((i, clip_index));
+                self.selected_track = Some(i);
+            }
+            if area.selectable && self.selected_clip == Some((i, clip_index)) {
+                painter.rect_stroke(
+                    clip_rect,
+                    0.1,
+                    egui::Stroke::new(2., ui.visuals().selection.stroke.color),
+                );
+            }
+        }
+
+        let row_top = max_rect.min.y + row as f32 * self.preview_y_scale;
+        let row_middle = row_top + self.preview_y_scale * 0.98 / 2.;
+        let mut name_x = max_rect.min.x;
+        if !track.take_lanes.is_empty() {
+            // Clicking the arrow in front of the name shows or hides the track's take lanes
+            let expanded = self.expanded_tracks.contains(&track.id);
+            let toggle_rect = egui::Rect::from_min_size(
+                egui::pos2(name_x, row_top),
+                egui::Vec2::splat(self.preview_y_scale),
             );
-            let color = if let Some(track_color) = track.color {
-                egui::Color32::from_hex(&self.colors[track_color]).unwrap()
-            } else {
-                egui::Color32::from_rgb(255, 0, 255)
-            };
-            painter.rect_filled(clip_rect, 0.1, color);
+            let toggle = ui
+                .interact(
+                    toggle_rect,
+                    area.id.with(("take lanes", i)),
+                    egui::Sense::click(),
+                )
+                .on_hover_text(format!("{} take lanes", track.take_lanes.len()));
+            if toggle.clicked() && !self.expanded_tracks.remove(&track.id) {
+                self.expanded_tracks.insert(track.id);
+            }
+            painter.text(
+                egui::pos2(name_x, row_middle),
+                egui::Align2::LEFT_CENTER,
+                if expanded { "⏷" } else { "⏵" },
+                egui::FontId::monospace(10.),
+                egui::Color32::from_rgb(0, 0, 255),
+            );
+            name_x = toggle_rect.max.x;
         }
-        painter.text(
-            egui::Pos2 {
-                x: max_rect.min.x,
-                y: max_rect.min.y
-                    + (i as f32 * self.preview_y_scale)
-                    + self.preview_y_scale * 0.98 / 2.,
-            },
+
+        let name_rect = painter.text(
+            egui::pos2(name_x, row_middle),
             egui::Align2::LEFT_CENTER,
             track.name.clone(),
             egui::FontId::monospace(10.),
-            egui::Color32::from_rgb(0, 0, 255),
+            // Tracks added, removed or renamed compared to the other set are named in orange
+            // and muted tracks are named faded
+            match (
+                area.changed_tracks.contains(&i),
+                area.muted_tracks.contains(&i),
+            ) {
+                (true, _) => egui::Color32::from_rgb(255, 140, 0),
+                (false, true) => egui::Color32::from_rgb(0, 0, 255).gamma_multiply(0.4),
+                (false, false) => egui::Color32::from_rgb(0, 0, 255),
+            },
+        );
+        // Clicking a track's name shows it in the inspector
+        if area.selectable
+            && ui
+                .interact(name_rect, area.id.with(("track", i)), egui::Sense::click())
+                .clicked()
+        {
+            self.selected_clip = None;
+            self.selected_track = Some(i);
+        }
+    }
+
+    /// Draws a take lane in a row below its track, takes are faded
+    /// apart from the parts of them which are comped into the track's clips
+    fn draw_take_lane(
+        &self,
+        ui: &mut egui::Ui,
+        (i, row): (usize, usize),
+        track: &Track,
+        lane: &TakeLane,
+        area: &PreviewArea,
+    ) {
+        let painter = &area.painter;
+        let max_rect = area.max_rect;
+        let color = self.track_color(track);
+
+        for (clip_index, clip) in lane.clips.iter().enumerate() {
+            let clip_rect = self.clip_rect(max_rect, row, clip.start, clip.end);
+            painter.rect_filled(clip_rect, 0.1, color.gamma_multiply(0.35));
+
+            let visible_rect = clip_rect.intersect(max_rect);
+            if visible_rect.is_positive() {
+                ui.interact(
+                    visible_rect,
+                    area.id.with(("take", i, row, clip_index)),
+                    egui::Sense::hover(),
+                )
+                .on_hover_ui(|ui| clip_details(ui, clip, area.time_signature));
+            }
+        }
+        for &(start, end) in &lane.comped {
+            painter.rect_filled(self.clip_rect(max_rect, row, start, end), 0.1, color);
+        }
+
+        let row_top = max_rect.min.y + row as f32 * self.preview_y_scale;
+        painter.text(
+            egui::pos2(max_rect.min.x, row_top + self.preview_y_scale * 0.98 / 2.),
+            egui::Align2::LEFT_CENTER,
+            if lane.audition {
+                format!("  {} (auditioned)", lane.name)
+            } else {
+                format!("  {}", lane.name)
+            },
+            egui::FontId::monospace(10.),
+            ui.visuals().weak_text_color(),
+        );
+    }
+
+    /// Where a clip from `start` to `end` in beats is drawn in the `row`th row of the preview
+    fn clip_rect(&self, max_rect: egui::Rect, row: usize, start: f32, end: f32) -> egui::Rect {
+        let row_top = max_rect.min.y + row as f32 * self.preview_y_scale;
+        egui::Rect::from_x_y_ranges(
+            egui::Rangef::new(
+                max_rect.min.x + self.preview_x_pos + start * self.preview_x_scale,
+                max_rect.min.x + self.preview_x_pos + end * self.preview_x_scale,
+            ),
+            egui::Rangef::new(row_top, row_top + self.preview_y_scale * 0.98),
+        )
+    }
+
+    /// Magenta for tracks without a colour or with one outside of the palette
+    fn track_color(&self, track: &Track) -> egui::Color32 {
+        track
+            .color
+            .and_then(|color| self.colors.get(color))
+            .and_then(|hex| egui::Color32::from_hex(hex).ok())
+            .unwrap_or(egui::Color32::from_rgb(255, 0, 255))
+    }
+
+    /// Deactivated clips are grey like in live, the clips of muted tracks are faded
+    fn clip_color(&self, track: &Track, clip: &Clip, muted: bool) -> egui::Color32 {
+        if clip.disabled {
+            egui::Color32::GRAY.gamma_multiply(0.5)
+        } else if muted {
+            self.track_color(track).gamma_multiply(0.35)
+        } else {
+            self.track_color(track)
+        }
+    }
+
+    /// Shows the selected track's routing and sends along with the exact timing and source
+    /// of the clip selected in the preview
+    pub fn inspector(&mut self, ui: &mut egui::Ui, selected_als_data: &AlsData) {
+        let Some(track) = self
+            .selected_track
+            .and_then(|track| selected_als_data.tracks.get(track))
+        else {
+            ui.label("Click a clip or track in the preview to inspect it...");
+            return;
+        };
+
+        ui.heading(&track.name);
+        let mut state = format!("{:?} track", track.kind);
+        if track.muted {
+            state += ", muted";
+        } else if selected_als_data.is_muted(track) {
+            state += ", muted by its group";
+        }
+        if track.frozen {
+            state += ", frozen";
+        } else if selected_als_data.is_frozen(track) {
+            state += ", frozen by its group";
+        }
+        ui.label(state);
+        egui::Grid::new("track_routing")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let routing = &track.routing;
+                for (label, routing) in [
+                    ("Audio from:", &routing.audio_input),
+                    ("Audio to:", &routing.audio_output),
+                    ("Midi from:", &routing.midi_input),
+                    ("Midi to:", &routing.midi_output),
+                ] {
+                    if let Some(routing) = routing {
+                        ui.label(label);
+                        ui.label(&routing.display);
+                        ui.end_row();
+                    }
+                }
+                for sidechain in &track.sidechains {
+                    ui.label(format!("{} sidechain:", sidechain.device));
+                    ui.label(&sidechain.input.display);
+                    ui.end_row();
+                }
+            });
+        let returns = selected_als_data
+            .tracks
+            .iter()
+            .filter(|track| track.kind == TrackKind::Return);
+        if !track.sends.is_empty() {
+            ui.separator();
+            ui.strong("Sends");
+            egui::Grid::new("track_sends")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (send, return_track) in track.sends.iter().zip(returns) {
+                        ui.label(&return_track.name);
+                        ui.label(format_send(send));
+                        ui.end_row();
+                    }
+                });
+        }
+        if !track.devices.is_empty() {
+            ui.separator();
+            ui.strong("Devices");
+            for device in &track.devices {
+                if device.on {
+                    ui.label(&device.name);
+                } else {
+                    ui.weak(format!("{} (off)", device.name));
+                }
+            }
+        }
+
+        let clip = self
+            .selected_clip
+            .filter(|&(clip_track, _)| self.selected_track == Some(clip_track))
+            .and_then(|(_, clip)| track.clips.get(clip));
+        if let Some(clip) = clip {
+            ui.separator();
+            clip_details(ui, clip, selected_als_data.time_signature);
+        }
+    }
+
+    /// Lists the changes from the compared set to the selected set
+    pub fn changes(&mut self, ui: &mut egui::Ui) {
+        let Some(ref shown_diff) = self.shown_diff else {
+            return;
+        };
+
+        ui.label(&shown_diff.summary);
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for change in &shown_diff.changes {
+                    ui.label(change);
+                }
+            });
+    }
+}
+
+/// Lists a clip's details, used by both the hover tooltip and the inspector
+fn clip_details(ui: &mut egui::Ui, clip: &Clip, time_signature: TimeSignature) {
+    egui::Grid::new("clip_details")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Name:");
+            if clip.name.is_empty() {
+                ui.label("Untitled");
+            } else {
+                ui.label(&clip.name);
+            }
+            ui.end_row();
+
+            ui.label("Start:");
+            ui.label(time_signature.format_position(clip.start));
+            ui.end_row();
+
+            ui.label("End:");
+            ui.label(time_signature.format_position(clip.end));
+            ui.end_row();
+
+            ui.label("Length:");
+            ui.label(time_signature.format_length(clip.end - clip.start));
+            ui.end_row();
+
+            if let Some(ref loop_data) = clip.loop_data {
+                ui.label("Loop:");
+                ui.label(format!(
+                    "{} - {}{}",
+                    time_signature.format_position(loop_data.start as f32),
+                    time_signature.format_position(loop_data.end as f32),
+                    if loop_data.on { "" } else { " (off)" }
+                ));
+                ui.end_row();
+            }
+
+            ui.label("Source:");
+            match clip.source {
+                Some(ref source) => ui.label(source),
+                None => ui.label(format!("{:?}", clip.kind)),
+            };
+            ui.end_row();
+
+            if clip.disabled {
+                ui.label("State:");
+                ui.label("Deactivated");
+                ui.end_row();
+            }
+        });
+}
+
+/// Indexes of the tracks of a set for which `filter` holds
+fn track_indexes(als_data: &AlsData, filter: fn(&AlsData, &Track) -> bool) -> Vec<usize> {
+    (0..als_data.tracks.len())
+        .filter(|&i| filter(als_data, &als_data.tracks[i]))
+        .collect()
+}
+
+/// Draws diagonal stripes over a clip like live does over the clips of frozen tracks,
+/// only across the part of it which is visible
+fn hatch(painter: &egui::Painter, rect: egui::Rect) {
+    let visible = rect.intersect(painter.clip_rect());
+    if !visible.is_positive() {
+        return;
+    }
+    let painter = painter.with_clip_rect(visible);
+    let stroke = egui::Stroke::new(1., egui::Color32::from_black_alpha(90));
+
+    let skipped = ((visible.min.x - rect.min.x) / HATCH_SPACING).floor() * HATCH_SPACING;
+    let mut x = rect.min.x + skipped - rect.height();
+    while x < visible.max.x {
+        painter.line_segment(
+            [
+                egui::pos2(x, rect.max.y),
+                egui::pos2(x + rect.height(), rect.min.y),
+            ],
+            stroke,
         );
+        x += HATCH_SPACING;
+    }
+}
+
+fn format_send(send: &TrackSend) -> String {
+    let level = match send.decibels() {
+        Some(decibels) => format!("{:.1} dB", decibels),
+        None => "-inf dB".to_string(),
+    };
+    if send.active {
+        level
+    } else {
+        format!("{} (off)", level)
     }
 }
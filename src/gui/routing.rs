@@ -0,0 +1,158 @@
+use eframe::egui;
+
+use super::gui::Gui;
+use crate::parse::als::AlsData;
+use crate::routing::{EdgeKind, RoutingGraph};
+
+const NODE_HEIGHT: f32 = 20.;
+const ROW_SPACING: f32 = 6.;
+const MIN_COLUMN_WIDTH: f32 = 170.;
+/// Room left right of each node for the edges to curve through
+const EDGE_SPACE: f32 = 50.;
+
+impl Gui {
+    /// Draws the tracks and groups of a set as nodes in columns, with edges for their audio and
+    /// midi routing, sends and sidechains. Hovering a node highlights the edges it is part of
+    pub fn routing_graph(&mut self, ui: &mut egui::Ui, selected_als_data: &AlsData) {
+        let graph = RoutingGraph::new(selected_als_data);
+
+        ui.horizontal(|ui| {
+            for kind in [
+                EdgeKind::Audio,
+                EdgeKind::Midi,
+                EdgeKind::Send,
+                EdgeKind::Sidechain,
+            ] {
+                ui.colored_label(edge_color(ui, kind), format!("━ {:?}", kind));
+            }
+        });
+        ui.separator();
+
+        let rows = (0..graph.columns)
+            .map(|column| graph.column(column).count())
+            .max()
+            .unwrap_or(0);
+
+        egui::ScrollArea::both()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                let column_width =
+                    (ui.available_width() / graph.columns as f32).max(MIN_COLUMN_WIDTH);
+                let (rect, response) = ui.allocate_exact_size(
+                    egui::vec2(
+                        column_width * graph.columns as f32,
+                        rows as f32 * (NODE_HEIGHT + ROW_SPACING),
+                    ),
+                    egui::Sense::hover(),
+                );
+                let painter = ui.painter_at(rect);
+
+                let mut node_rects = vec![egui::Rect::NOTHING; graph.nodes.len()];
+                for column in 0..graph.columns {
+                    for (row, (index, _)) in graph.column(column).enumerate() {
+                        node_rects[index] = egui::Rect::from_min_size(
+                            rect.min
+                                + egui::vec2(
+                                    column as f32 * column_width,
+                                    row as f32 * (NODE_HEIGHT + ROW_SPACING),
+                                ),
+                            egui::vec2(column_width - EDGE_SPACE, NODE_HEIGHT),
+                        );
+                    }
+                }
+                let hovered = response
+                    .hover_pos()
+                    .and_then(|pointer| node_rects.iter().position(|rect| rect.contains(pointer)));
+
+                for edge in &graph.edges {
+                    let highlighted =
+                        hovered.is_none_or(|node| edge.from == node || edge.to == node);
+                    let color = edge_color(ui, edge.kind);
+                    let stroke = if highlighted {
+                        egui::Stroke::new(2., color)
+                    } else {
+                        egui::Stroke::new(1., color.gamma_multiply(0.2))
+                    };
+
+                    let from = node_rects[edge.from].right_center();
+                    let to = node_rects[edge.to].left_center();
+                    let bend = ((to.x - from.x).abs() / 2.).max(EDGE_SPACE);
+                    painter.add(egui::epaint::CubicBezierShape::from_points_stroke(
+                        [
+                            from,
+                            from + egui::vec2(bend, 0.),
+                            to - egui::vec2(bend, 0.),
+                            to,
+                        ],
+                        false,
+                        egui::Color32::TRANSPARENT,
+                        stroke,
+                    ));
+                }
+
+                for (index, node) in graph.nodes.iter().enumerate() {
+                    let node_rect = node_rects[index];
+                    let fill = if hovered == Some(index) {
+                        ui.visuals().widgets.hovered.bg_fill
+                    } else {
+                        ui.visuals().widgets.inactive.bg_fill
+                    };
+                    painter.rect_filled(node_rect, 3., fill);
+
+                    // A stripe of the track's colour like the track headers in live
+                    let stripe = egui::Rect::from_min_size(
+                        node_rect.min,
+                        egui::vec2(4., node_rect.height()),
+                    );
+                    let color = node
+                        .color
+                        .and_then(|color| self.colors.get(color))
+                        .and_then(|hex| egui::Color32::from_hex(hex).ok())
+                        .unwrap_or(egui::Color32::GRAY);
+                    painter.rect_filled(stripe, 3., color);
+
+                    painter.with_clip_rect(node_rect).text(
+                        node_rect.left_center() + egui::vec2(8., 0.),
+                        egui::Align2::LEFT_CENTER,
+                        &node.name,
+                        egui::FontId::proportional(12.),
+                        ui.visuals().text_color(),
+                    );
+                }
+
+                if let Some(node) = hovered {
+                    response.on_hover_ui_at_pointer(|ui| {
+                        ui.strong(&graph.nodes[node].name);
+                        for edge in graph
+                            .edges
+                            .iter()
+                            .filter(|e| e.from == node || e.to == node)
+                        {
+                            let (direction, other) = if edge.from == node {
+                                ("to", edge.to)
+                            } else {
+                                ("from", edge.from)
+                            };
+                            let mut text = format!(
+                                "{:?} {} {}",
+                                edge.kind, direction, graph.nodes[other].name
+                            );
+                            if !edge.label.is_empty() {
+                                text += &format!(" ({})", edge.label);
+                            }
+                            ui.label(text);
+                        }
+                    });
+                }
+            });
+    }
+}
+
+fn edge_color(ui: &egui::Ui, kind: EdgeKind) -> egui::Color32 {
+    match kind {
+        EdgeKind::Audio => ui.visuals().text_color(),
+        EdgeKind::Midi => egui::Color32::from_rgb(230, 200, 60),
+        EdgeKind::Send => egui::Color32::from_rgb(80, 160, 255),
+        EdgeKind::Sidechain => egui::Color32::from_rgb(255, 140, 0),
+    }
+}
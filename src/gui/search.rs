@@ -0,0 +1,117 @@
+use crate::gui::gui::Gui;
+use crate::search::ContainsKind;
+use eframe::egui::{self, text::LayoutJob, Color32, TextFormat, TextStyle};
+
+impl Gui {
+    /// A search box above the als file list along with filters on the parsed sets
+    pub fn search_bar(&mut self, ui: &mut egui::Ui, shown: usize, total: usize) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search.query)
+                    .hint_text("Search sets...")
+                    .desired_width(ui.available_width() - 90.),
+            );
+            if self.search.is_active() && ui.button("Clear").clicked() {
+                self.search = Default::default();
+            }
+        });
+
+        egui::CollapsingHeader::new("Filters")
+            .id_source("search_filters")
+            .show(ui, |ui| {
+                egui::Grid::new("search_filters_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let search = &mut self.search;
+
+                        let mut use_tempo = search.tempo.is_some();
+                        ui.checkbox(&mut use_tempo, "Tempo");
+                        let (min, max) = search.tempo.get_or_insert((60., 200.));
+                        ui.add_enabled_ui(use_tempo, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(min).clamp_range(0.0..=*max));
+                                ui.label("to");
+                                ui.add(egui::DragValue::new(max).clamp_range(*min..=999.));
+                            });
+                        });
+                        if !use_tempo {
+                            search.tempo = None;
+                        }
+                        ui.end_row();
+
+                        let mut use_track_count = search.track_count.is_some();
+                        ui.checkbox(&mut use_track_count, "Tracks");
+                        let (min, max) = search.track_count.get_or_insert((1, 32));
+                        ui.add_enabled_ui(use_track_count, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(min).clamp_range(0..=*max));
+                                ui.label("to");
+                                ui.add(egui::DragValue::new(max).clamp_range(*min..=999));
+                            });
+                        });
+                        if !use_track_count {
+                            search.track_count = None;
+                        }
+                        ui.end_row();
+
+                        let mut use_modified = search.modified_within_days.is_some();
+                        ui.checkbox(&mut use_modified, "Modified");
+                        let days = search.modified_within_days.get_or_insert(30);
+                        ui.add_enabled_ui(use_modified, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("in the last");
+                                ui.add(egui::DragValue::new(days).clamp_range(1..=36500));
+                                ui.label("days");
+                            });
+                        });
+                        if !use_modified {
+                            search.modified_within_days = None;
+                        }
+                        ui.end_row();
+
+                        egui::ComboBox::from_id_source("search_contains_kind")
+                            .selected_text(search.contains_kind.label())
+                            .show_ui(ui, |ui| {
+                                for kind in ContainsKind::ALL {
+                                    ui.selectable_value(
+                                        &mut search.contains_kind,
+                                        kind,
+                                        kind.label(),
+                                    );
+                                }
+                            });
+                        ui.add(
+                            egui::TextEdit::singleline(&mut search.contains)
+                                .hint_text("contains..."),
+                        );
+                        ui.end_row();
+                    });
+            });
+
+        if self.search.is_active() {
+            ui.label(format!("Showing {} of {} sets", shown, total));
+        }
+        ui.separator();
+    }
+}
+
+/// Lays out a set name with the characters matched by the search highlighted
+pub fn highlighted(ui: &egui::Ui, text: &str, matched: &[usize]) -> LayoutJob {
+    let font_id = TextStyle::Button.resolve(ui.style());
+    let highlight = ui.visuals().warn_fg_color;
+
+    let mut job = LayoutJob::default();
+    for (i, c) in text.chars().enumerate() {
+        let color = if matched.contains(&i) {
+            highlight
+        } else {
+            Color32::PLACEHOLDER
+        };
+        job.append(
+            c.encode_utf8(&mut [0; 4]),
+            0.,
+            TextFormat::simple(font_id.clone(), color),
+        );
+    }
+    job
+}
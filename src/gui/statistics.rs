@@ -0,0 +1,204 @@
+use eframe::egui;
+use std::{path::Path, thread};
+
+use super::gui::{format_size, Gui};
+use crate::{
+    history::BackupTimestamp,
+    parse::als::Project,
+    search,
+    stats::{LibraryStats, TEMPO_BUCKET},
+};
+
+/// How many of the most used plugins are listed
+const TOP_PLUGINS: usize = 15;
+const SECONDS_PER_MONTH: u64 = 30 * 24 * 60 * 60;
+
+impl Gui {
+    /// Summarises the whole library, the statistics are worked out in the background
+    /// whenever projects were loaded or changed since they were last shown
+    pub fn statistics(&mut self, ui: &mut egui::Ui, projects: &[Project]) {
+        if self
+            .statistics_job
+            .as_ref()
+            .is_some_and(|job| job.is_finished())
+        {
+            if let Some(Ok(statistics)) = self.statistics_job.take().map(|job| job.join()) {
+                self.statistics = Some(statistics);
+            }
+        }
+        if self.statistics_outdated && self.statistics_job.is_none() {
+            self.statistics_outdated = false;
+            let projects = projects.to_vec();
+            self.statistics_job = Some(thread::spawn(move || {
+                LibraryStats::new(&projects, Path::new("projects/"))
+            }));
+        }
+
+        let Some(statistics) = self.statistics.clone() else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Gathering statistics...");
+            });
+            return;
+        };
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                ui.heading("Library");
+                egui::Grid::new("library_statistics")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let rows = [
+                            ("Projects:", statistics.projects.len().to_string()),
+                            ("Sets:", statistics.sets.to_string()),
+                            ("Tracks:", statistics.tracks.to_string()),
+                            ("Clips:", statistics.clips.to_string()),
+                            (
+                                "Average length:",
+                                format!(
+                                    "{:.0} bars, {}:{:02}",
+                                    statistics.average_bars,
+                                    statistics.average_seconds as u32 / 60,
+                                    statistics.average_seconds as u32 % 60
+                                ),
+                            ),
+                            ("Disk usage:", format_size(statistics.disk_usage())),
+                        ];
+                        for (label, value) in rows {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.heading("Tempo");
+                tempo_histogram(ui, &statistics.tempos);
+
+                ui.separator();
+                ui.heading("Most used plugins");
+                ui.label("Only plugins directly on tracks are counted, not those inside racks");
+                if statistics.plugins.is_empty() {
+                    ui.label("No plugins are used");
+                }
+                egui::Grid::new("plugin_statistics")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (plugin, sets) in statistics.plugins.iter().take(TOP_PLUGINS) {
+                            ui.label(plugin);
+                            ui.label(format!("{} sets", sets));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.heading("Projects");
+                egui::Grid::new("project_statistics")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Project", "Sets", "Backups", "Tracks", "Clips", "Disk"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for project in &statistics.projects {
+                            ui.label(&project.name);
+                            ui.label(project.sets.to_string());
+                            ui.label(project.backups.to_string());
+                            ui.label(project.tracks.to_string());
+                            ui.label(project.clips.to_string());
+                            ui.label(format_size(project.disk_usage));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.heading("Not saved in");
+                    let response = ui.add(
+                        egui::DragValue::new(&mut self.settings.stale_months)
+                            .clamp_range(1..=120)
+                            .suffix(" months"),
+                    );
+                    if response.changed() {
+                        if let Err(e) = self.settings.save() {
+                            self.status = Some(e);
+                        }
+                    }
+                });
+
+                let before = search::now()
+                    .saturating_sub(self.settings.stale_months as u64 * SECONDS_PER_MONTH);
+                egui::Grid::new("untouched_sets")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (project, set, modified) in statistics.untouched_since(before) {
+                            ui.label(set);
+                            ui.label(project);
+                            ui.label(BackupTimestamp::from_unix_local(*modified).to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+/// Draws how many sets there are of each range of tempos as a bar chart
+fn tempo_histogram(ui: &mut egui::Ui, tempos: &[(f32, usize)]) {
+    let Some(most) = tempos.iter().map(|(_, sets)| *sets).max() else {
+        ui.label("No sets are loaded");
+        return;
+    };
+
+    let bars_height = 100.;
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), bars_height + 16.),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    let bar_width = rect.width() / tempos.len() as f32;
+    // Labels every few bars when they are too narrow to each have one
+    let label_every = (30. / bar_width).ceil().max(1.) as usize;
+
+    for (i, (tempo, sets)) in tempos.iter().enumerate() {
+        let left = rect.left() + i as f32 * bar_width;
+        let column = egui::Rect::from_x_y_ranges(left..=left + bar_width, rect.y_range());
+        let hovered = response
+            .hover_pos()
+            .is_some_and(|pointer| column.contains(pointer));
+
+        let height = *sets as f32 / most as f32 * bars_height;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left + 1., rect.top() + bars_height - height),
+            egui::pos2(left + bar_width - 1., rect.top() + bars_height),
+        );
+        let color = if hovered {
+            ui.visuals().selection.stroke.color
+        } else {
+            ui.visuals().selection.bg_fill
+        };
+        painter.rect_filled(bar, 2., color);
+
+        if i % label_every == 0 {
+            painter.text(
+                egui::pos2(left + bar_width / 2., rect.top() + bars_height + 2.),
+                egui::Align2::CENTER_TOP,
+                format!("{}", tempo),
+                egui::FontId::proportional(11.),
+                ui.visuals().text_color(),
+            );
+        }
+        if hovered {
+            response.clone().on_hover_text(format!(
+                "{} to {} bpm: {} sets",
+                tempo,
+                tempo + TEMPO_BUCKET,
+                sets
+            ));
+        }
+    }
+}
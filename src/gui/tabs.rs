@@ -1,15 +1,23 @@
-use crate::gui::gui::Gui;
+use crate::diff;
+use crate::gui::gui::{Gui, ShownDiff, SALVAGED_WARNING};
+use crate::parse::als::{AlsData, Project};
 use eframe::egui;
 use egui_dock::{DockArea, DockState, NodeIndex, Split, Style};
 
 struct TabViewer<'a> {
     gui_handle: &'a mut Gui,
+    /// Taken out of the gui while the tabs are shown so they can borrow it
+    projects: Option<&'a [Project]>,
 }
 
 #[derive(Clone, PartialEq)]
 pub enum TabType {
     AlsFileList,
     AlsViewer,
+    Inspector,
+    Changes,
+    Statistics,
+    Routing,
 }
 
 impl<'a> egui_dock::TabViewer for TabViewer<'a> {
@@ -19,11 +27,15 @@ impl<'a> egui_dock::TabViewer for TabViewer<'a> {
         match tab {
             TabType::AlsFileList => "Als File List".to_string().into(),
             TabType::AlsViewer => "Als Viewer".to_string().into(),
+            TabType::Inspector => "Inspector".to_string().into(),
+            TabType::Changes => "Changes".to_string().into(),
+            TabType::Statistics => "Statistics".to_string().into(),
+            TabType::Routing => "Routing".to_string().into(),
         }
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
-        if let Some(ref projects) = &self.gui_handle.projects.clone() {
+        if let Some(projects) = self.projects {
             match tab {
                 TabType::AlsFileList => {
                     self.gui_handle.selected_project_als = self.gui_handle.als_panel(ui, projects);
@@ -32,34 +44,155 @@ impl<'a> egui_dock::TabViewer for TabViewer<'a> {
                     if let Some((selected_project, selected_als)) =
                         self.gui_handle.selected_project_als
                     {
-                        let selected_als_data =
-                            projects[selected_project].als_data.as_ref().unwrap()[selected_als]
-                                .clone();
+                        let project = &projects[selected_project];
+                        let set_name = &project.als_data.as_ref().unwrap()[selected_als].name;
+                        self.gui_handle.history_timeline(ui, project, set_name);
 
-                        self.gui_handle.visual_preview(ui, selected_als_data);
+                        self.gui_handle.update_shown_diff(projects);
+                        let (selected_als_data, compare_als_data) =
+                            self.gui_handle.shown_als_data(projects).unwrap();
+                        for als_data in [Some(selected_als_data), compare_als_data].iter().flatten()
+                        {
+                            if als_data.salvaged {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!("{}: {}", als_data.name, SALVAGED_WARNING),
+                                );
+                            }
+                        }
+
+                        self.gui_handle
+                            .visual_preview(ui, selected_als_data, compare_als_data);
                     } else {
                         ui.label(egui::RichText::new("Please choose a file...").size(40.));
                     }
                     ui.add_space(50.);
                 }
+                TabType::Inspector => {
+                    if let Some((selected_als_data, _)) = self.gui_handle.shown_als_data(projects) {
+                        self.gui_handle.inspector(ui, selected_als_data);
+                    }
+                }
+                TabType::Changes => {
+                    self.gui_handle.update_shown_diff(projects);
+                    if self.gui_handle.shown_diff.is_some() {
+                        self.gui_handle.changes(ui);
+                    } else {
+                        ui.label("Right click a set to compare it with the selected set...");
+                    }
+                }
+                TabType::Statistics => self.gui_handle.statistics(ui, projects),
+                TabType::Routing => {
+                    if let Some((selected_als_data, _)) = self.gui_handle.shown_als_data(projects) {
+                        self.gui_handle.routing_graph(ui, selected_als_data);
+                    } else {
+                        ui.label("Please choose a file...");
+                    }
+                }
             }
         }
     }
 }
 
 impl Gui {
+    /// The set shown in the viewer along with the set it is compared against, if any,
+    /// a backup chosen on the history timeline replaces the selected set unless it is being
+    /// compared with the current version
+    pub fn shown_als_data<'a>(
+        &self,
+        projects: &'a [Project],
+    ) -> Option<(&'a AlsData, Option<&'a AlsData>)> {
+        let (selected_project, selected_als) = self.selected_project_als?;
+        let project = projects.get(selected_project)?;
+        let selected_als_data = project.als_data.as_ref()?.get(selected_als)?;
+        let compare_als_data =
+            self.compare_project_als
+                .and_then(|(compare_project, compare_als)| {
+                    projects
+                        .get(compare_project)?
+                        .als_data
+                        .as_ref()?
+                        .get(compare_als)
+                });
+
+        match self
+            .history_version
+            .and_then(|index| project.backups.get(index))
+        {
+            Some(backup) if self.compare_with_history => Some((selected_als_data, Some(backup))),
+            Some(backup) => Some((backup, compare_als_data)),
+            None => Some((selected_als_data, compare_als_data)),
+        }
+    }
+
+    /// Works out the diff of the shown sets again when other sets are shown
+    pub fn update_shown_diff(&mut self, projects: &[Project]) {
+        let shown = (
+            self.selected_project_als,
+            self.compare_project_als,
+            self.history_version,
+            self.compare_with_history,
+        );
+        if self
+            .shown_diff
+            .as_ref()
+            .is_some_and(|shown_diff| shown_diff.shown == shown)
+        {
+            return;
+        }
+
+        self.shown_diff = match self.shown_als_data(projects) {
+            Some((selected_als_data, Some(compare_als_data))) => {
+                // The compared set is usually an older version of the selected one
+                let als_diff = diff::diff(compare_als_data, selected_als_data);
+                Some(ShownDiff {
+                    shown,
+                    summary: format!(
+                        "{} changes from {} to {}",
+                        als_diff.changes.len(),
+                        compare_als_data.name,
+                        selected_als_data.name
+                    ),
+                    changes: als_diff
+                        .changes
+                        .iter()
+                        .map(|change| als_diff.describe(change))
+                        .collect(),
+                    changed_new: (als_diff.changed_new_clips(), als_diff.changed_new_tracks()),
+                    changed_old: (als_diff.changed_old_clips(), als_diff.changed_old_tracks()),
+                })
+            }
+            _ => None,
+        };
+    }
+
     pub fn default_tab_layout() -> DockState<TabType> {
         let mut dock_state = DockState::new(vec![TabType::AlsFileList]);
 
         // Get the index of the root node, which is always 0
         let root_index: NodeIndex = 0.into();
 
-        dock_state.split(
+        let [_, viewer_index] = dock_state.split(
             (egui_dock::SurfaceIndex::main(), root_index),
             Split::Right,
             0.25,
-            egui_dock::Node::leaf(TabType::AlsViewer),
+            egui_dock::Node::leaf_with(vec![TabType::AlsViewer, TabType::Routing]),
+        );
+
+        let [_, inspector_index] = dock_state.split(
+            (egui_dock::SurfaceIndex::main(), viewer_index),
+            Split::Right,
+            0.75,
+            egui_dock::Node::leaf(TabType::Inspector),
         );
+        dock_state.main_surface_mut()[inspector_index].append_tab(TabType::Changes);
+        dock_state.main_surface_mut()[inspector_index].append_tab(TabType::Statistics);
+        // Appending a tab makes it active, the changes are shown first
+        dock_state.set_active_tab((
+            egui_dock::SurfaceIndex::main(),
+            inspector_index,
+            egui_dock::TabIndex(1),
+        ));
 
         dock_state
     }
@@ -71,13 +204,19 @@ impl Gui {
         // Replace self.dock_state with the placeholder and take the original
         let mut dock_state = std::mem::replace(&mut self.dock_state, placeholder_dock_state);
 
-        let mut tab_viewer = TabViewer { gui_handle: self };
+        // The tabs borrow the projects while changing the rest of the gui
+        let projects = self.projects.take();
+        let mut tab_viewer = TabViewer {
+            gui_handle: self,
+            projects: projects.as_deref(),
+        };
         DockArea::new(&mut dock_state)
             .style(Style::from_egui(&ctx.style()))
             .show_close_buttons(false)
             .draggable_tabs(false)
             .show(ctx, &mut tab_viewer);
 
+        self.projects = projects;
         self.dock_state = dock_state;
     }
 }
@@ -0,0 +1,79 @@
+use eframe::egui;
+
+use super::gui::Gui;
+use crate::{history, parse::als::Project};
+
+impl Gui {
+    /// Draws the selected set's backups on a timeline which can be clicked or dragged across
+    /// to scrub through older versions in the preview
+    pub fn history_timeline(&mut self, ui: &mut egui::Ui, project: &Project, set_name: &str) {
+        let versions = history::history(project, set_name);
+        if versions.is_empty() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let shown = self
+                .history_version
+                .and_then(|index| versions.iter().find(|v| v.backup_index == index));
+            match shown {
+                Some(version) => ui.label(format!("Backup from {}", version.timestamp)),
+                None => ui.label("Current version"),
+            };
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.checkbox(&mut self.compare_with_history, "Compare with current");
+            });
+        });
+
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), 20.),
+            egui::Sense::click_and_drag(),
+        );
+        let rect = rect.shrink2(egui::vec2(8., 0.));
+        let painter = ui.painter_at(rect.expand(8.));
+
+        // Versions are spaced evenly with the current version at the far right
+        let x_positions: Vec<f32> = (0..=versions.len())
+            .map(|i| rect.left() + rect.width() * i as f32 / versions.len() as f32)
+            .collect();
+
+        painter.hline(
+            rect.x_range(),
+            rect.center().y,
+            ui.visuals().widgets.noninteractive.fg_stroke,
+        );
+        for (i, &x) in x_positions.iter().enumerate() {
+            let backup_index = versions.get(i).map(|version| version.backup_index);
+            let color = if backup_index == self.history_version {
+                ui.visuals().selection.stroke.color
+            } else {
+                ui.visuals().widgets.inactive.fg_stroke.color
+            };
+            painter.circle_filled(egui::pos2(x, rect.center().y), 5., color);
+        }
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let nearest = x_positions
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| (*a - pointer.x).abs().total_cmp(&(*b - pointer.x).abs()))
+                .map(|(i, _)| i)
+                .unwrap();
+
+            let version = versions.get(nearest).map(|version| version.backup_index);
+            if version != self.history_version {
+                self.history_version = version;
+                self.selected_clip = None;
+                self.selected_track = None;
+            }
+        }
+
+        response.on_hover_text(format!(
+            "{} backups from {} to {}",
+            versions.len(),
+            versions[0].timestamp,
+            versions[versions.len() - 1].timestamp
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::parse::als::Project;
+
+/// The time ableton saved a backup, parsed from its file name
+/// such as `Song [2021-11-26 134901]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BackupTimestamp {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+/// A backup of a set along with when it was saved
+pub struct Version {
+    pub timestamp: BackupTimestamp,
+    /// Index of the backup within `Project::backups`
+    pub backup_index: usize,
+}
+
+/// Collects the backups of a set, oldest first
+pub fn history(project: &Project, set_name: &str) -> Vec<Version> {
+    let mut versions: Vec<Version> = project
+        .backups
+        .iter()
+        .enumerate()
+        .filter_map(|(backup_index, als_data)| {
+            let (name, timestamp) = split_backup_name(&als_data.name)?;
+            (name == set_name).then_some(Version {
+                timestamp,
+                backup_index,
+            })
+        })
+        .collect();
+
+    versions.sort_by_key(|version| version.timestamp);
+    versions
+}
+
+/// Splits a backup's file name into the set name and the time it was saved
+pub fn split_backup_name(backup_name: &str) -> Option<(&str, BackupTimestamp)> {
+    let (name, timestamp) = backup_name.strip_suffix(']')?.rsplit_once(" [")?;
+    Some((name, timestamp.parse().ok()?))
+}
+
+impl BackupTimestamp {
+    /// The UTC time of a number of seconds since the unix epoch, such as `AlsData::modified`
+    pub fn from_unix(seconds: u64) -> BackupTimestamp {
+        let days = (seconds / 86400) as i64;
+        let time = seconds % 86400;
+
+        // Converts days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        BackupTimestamp {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+            hour: (time / 3600) as u32,
+            minute: (time % 3600 / 60) as u32,
+            second: (time % 60) as u32,
+        }
+    }
+
+    /// The local time of a number of seconds since the unix epoch, for showing dates to the user
+    pub fn from_unix_local(seconds: u64) -> BackupTimestamp {
+        let local = seconds.saturating_add_signed(utc_offset(seconds));
+        BackupTimestamp::from_unix(local)
+    }
+}
+
+/// Seconds the local time zone is ahead of UTC at a time
+#[cfg(unix)]
+fn utc_offset(seconds: u64) -> i64 {
+    let Ok(time) = libc::time_t::try_from(seconds) else {
+        return 0;
+    };
+    // SAFETY: localtime_r only writes to the tm it is given, which is zeroed plain data
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    }
+}
+
+/// Seconds the local time zone is ahead of UTC, dates are shown in UTC where it is not known
+#[cfg(not(unix))]
+fn utc_offset(_seconds: u64) -> i64 {
+    0
+}
+
+impl std::str::FromStr for BackupTimestamp {
+    type Err = String;
+
+    /// Parses timestamps in the `2021-11-26 134901` format ableton uses
+    fn from_str(timestamp: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid backup timestamp: {}", timestamp);
+        let number = |digits: &str| digits.parse::<u32>().map_err(|_| error());
+
+        let (date, time) = timestamp.split_once(' ').ok_or_else(error)?;
+        let mut date = date.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (date.next(), date.next(), date.next(), date.next())
+        else {
+            return Err(error());
+        };
+        if time.len() != 6 || !time.is_ascii() {
+            return Err(error());
+        }
+
+        Ok(BackupTimestamp {
+            year: number(year)? as i32,
+            month: number(month)?,
+            day: number(day)?,
+            hour: number(&time[0..2])?,
+            minute: number(&time[2..4])?,
+            second: number(&time[4..6])?,
+        })
+    }
+}
+
+impl fmt::Display for BackupTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, MutexGuard,
+    },
+};
+
+use crate::message::Message;
+use crate::palsa::{self, ProjectFiles};
+use crate::progress::Progress;
+
+/// Loads projects in the background one at a time, sending each to the gui as soon as it is
+/// loaded. The gui shares the loader to move sets to the front of the queue or cancel loading
+#[derive(Clone, Default)]
+pub struct Loader {
+    queue: Arc<Mutex<VecDeque<ProjectFiles>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Loader {
+    pub fn new() -> Loader {
+        Loader::default()
+    }
+
+    /// Finds the projects in `dir` then loads them until none are left or loading is cancelled.
+    /// Returns whether the gui is still listening
+    pub fn run(&self, dir: &Path, sender: &mpsc::Sender<Message>, progress: &Progress) -> bool {
+        let found = match palsa::discover(dir, progress) {
+            Ok(found) => found,
+            Err(error) => {
+                let _ = sender.send(Message::Error(error));
+                return false;
+            }
+        };
+
+        let discovered = found
+            .iter()
+            .map(|project_files| (project_files.name.clone(), project_files.set_names()))
+            .collect();
+        if sender.send(Message::Discovered(discovered)).is_err() {
+            return false;
+        }
+        self.lock_queue().extend(found);
+
+        while let Some(project_files) = self.next() {
+            let message = match palsa::load_project(&project_files, progress, &self.cancelled) {
+                // Projects cut short by cancelling stay unloaded
+                Ok(_) if self.is_cancelled() => break,
+                Ok(project) => Message::ProjectLoaded(project),
+                Err(error) => Message::Error(error),
+            };
+            if sender.send(message).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Moves a set to the front of the queue so it is loaded next, on its own ahead of the rest
+    /// of its project which keeps its place
+    pub fn prioritise(&self, project_name: &str, set_name: &str) {
+        let mut queue = self.lock_queue();
+        let Some(project_files) = queue.iter_mut().find(|p| p.name == project_name) else {
+            return;
+        };
+        let Some(index) = project_files.files.iter().position(|file| {
+            Path::new(file)
+                .file_stem()
+                .is_some_and(|stem| stem == set_name)
+        }) else {
+            return;
+        };
+
+        let file = project_files.files.remove(index);
+        queue.push_front(ProjectFiles {
+            name: project_name.to_string(),
+            files: vec![file],
+            backup_files: Vec::new(),
+        });
+    }
+
+    /// Stops loading, sets which are already being parsed are finished but not shown
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.lock_queue().clear();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn next(&self) -> Option<ProjectFiles> {
+        if self.is_cancelled() {
+            return None;
+        }
+        self.lock_queue().pop_front()
+    }
+
+    fn lock_queue(&self) -> MutexGuard<'_, VecDeque<ProjectFiles>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -17,3 +17,19 @@ macro_rules! get_attribute_value {
             .to_string()
     }};
 }
+
+/// Like `get_attribute_value` but gives `None` instead of panicking when a tag is missing
+#[macro_export]
+macro_rules! find_attribute_value {
+    ($node:expr, $($tag_name:expr),*) => {{
+        let current_node = Some($node);
+        $(
+            let current_node = current_node.and_then(|node| {
+                node.descendants().find(|n| n.has_tag_name($tag_name))
+            });
+        )*
+        current_node
+            .and_then(|node| node.attribute("Value"))
+            .map(|value| value.to_string())
+    }};
+}
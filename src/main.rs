@@ -1,34 +1,90 @@
 use std::path::Path;
-use std::{fs, io};
+use std::{env, fs, io, process};
 
 // Used for multithreading of initial file loading to separate it from the gui
 use std::sync::mpsc;
 use std::thread;
 
 // All project modules
-use parse::als::Project;
+use loader::Loader;
+use message::Message;
+use progress::Progress;
+use settings::Settings;
+#[cfg(feature = "bench")]
+mod bench;
 mod cache;
+mod cli;
+mod colors;
+mod diff;
+mod edit;
+mod export;
 mod extract;
 mod gui;
+mod history;
+mod loader;
 use gui::gui::Gui;
 pub mod macros;
+mod message;
 mod palsa;
 mod parallel;
 mod parse;
+mod progress;
+mod render;
+mod routing;
+mod rules;
+mod samples;
+mod search;
+mod settings;
+mod sort;
+mod stats;
+mod trash;
+mod watcher;
+
+// Counts allocations so `palsa bench` can report peak memory use
+#[cfg(feature = "bench")]
+#[global_allocator]
+static ALLOCATOR: bench::PeakAllocator = bench::PeakAllocator;
 
 fn main() -> io::Result<()> {
-    let (sender, receiver) = mpsc::channel::<Result<Vec<Project>, String>>();
+    extract::set_size_limit(
+        Settings::load()
+            .max_decompressed_mb
+            .saturating_mul(1_000_000),
+    );
+
+    // Any arguments run a command line tool instead of the gui
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(error) = cli::run(&args) {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel::<Message>();
 
     let dir: &Path = Path::new("projects/");
     fs::create_dir_all(dir)?;
 
-    // Creates a thread to run palsa in parralel with ui for initial file loading
+    // Creates a thread to run palsa in parralel with ui for initial file loading,
+    // it then keeps watching the projects for sets which change
+    let loader = Loader::new();
+    let background_loader = loader.clone();
     thread::spawn(move || {
-        let projects = palsa::run_palsa(dir);
-        sender.send(projects).unwrap();
+        let snapshot = watcher::snapshot(dir);
+
+        let progress_sender = sender.clone();
+        let progress = Progress::new(move |report| {
+            let _ = progress_sender.send(Message::Progress(report.clone()));
+        });
+
+        if background_loader.run(dir, &sender, &progress) {
+            watcher::watch(dir, snapshot, sender);
+        }
     });
 
-    let gui = Gui::new(receiver);
+    let gui = Gui::new(receiver, loader);
     gui.run();
 
     Ok(())
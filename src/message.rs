@@ -0,0 +1,26 @@
+use crate::parse::als::{AlsData, Project};
+use crate::progress::ProgressReport;
+
+/// What the loading thread sends to the gui over its channel
+pub enum Message {
+    /// How far loading has come, sent whenever a set is found or loaded
+    Progress(ProgressReport),
+    /// The name of every project found along with the names of its sets, before any are loaded
+    Discovered(Vec<(String, Vec<String>)>),
+    /// A project whose sets were all loaded
+    ProjectLoaded(Project),
+    /// A set was added or saved since it was loaded
+    SetChanged {
+        project: String,
+        /// Whether the set is in the project's `Backup` directory
+        backup: bool,
+        als_data: AlsData,
+    },
+    /// A set was deleted or renamed
+    SetRemoved {
+        project: String,
+        backup: bool,
+        name: String,
+    },
+    Error(String),
+}
@@ -1,60 +1,153 @@
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
 
 use crate::cache;
+use crate::extract;
 use crate::parallel;
-use crate::parse::als::Project;
+use crate::parse::als::{AlsData, Project};
+use crate::progress::Progress;
 
 /// Extracts, parses and creates cache of all *als* files
 /// of the following depth:
-/// ```
+/// ```text
 /// projects
 /// -------- dir_0
 ///          ----- als_0.als
 ///          ----- als_1.als
+///          ----- Backup
+///                ----- als_0 [2024-01-01 120000].als
 /// -------- dir_1
 ///          ----- als_0.als
 ///          ----- als_1.als
 /// ```
-
-pub fn run_palsa(dir: &Path) -> Result<Vec<Project>, String> {
+pub fn run_palsa(dir: &Path, progress: &Progress) -> Result<Vec<Project>, String> {
     let mut projects: Vec<Project> = Vec::new();
 
     let mut errors = Vec::new();
 
+    let not_cancelled = AtomicBool::new(false);
+    for project_files in discover(dir, progress)? {
+        match load_project(&project_files, progress, &not_cancelled) {
+            Ok(project) => projects.push(project),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join(", "));
+    }
+
+    Ok(projects)
+}
+
+/// A project directory along with the *als* files of it which are yet to be loaded
+#[derive(Debug, Clone)]
+pub struct ProjectFiles {
+    pub name: String,
+    pub files: Vec<String>,
+    /// Older versions of the sets in the project's `Backup` directory
+    pub backup_files: Vec<String>,
+}
+
+impl ProjectFiles {
+    /// Names of the project's sets, excluding backups
+    pub fn set_names(&self) -> Vec<String> {
+        self.files
+            .iter()
+            .filter_map(|file| Some(Path::new(file).file_stem()?.to_str()?.to_string()))
+            .collect()
+    }
+}
+
+/// Finds every project's sets so the total is known before parsing starts
+/// Caches written by another version of palsa are cleared first
+pub fn discover(dir: &Path, progress: &Progress) -> Result<Vec<ProjectFiles>, String> {
+    cache::clear_outdated().map_err(|e| format!("Failed to clear the outdated cache: {}", e))?;
+
+    let mut found = Vec::new();
+
     for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
         let entry = entry.map_err(|e| e.to_string())?;
         let path = entry.path();
 
         if path.is_dir() {
-            let project_name = path
+            let name = path
                 .file_name()
                 .and_then(|stem| stem.to_str())
                 .ok_or_else(|| "Invalid project directory name".to_string())?
                 .to_string();
 
-            match parallel::parallel_parse_dir(&project_name, path.to_str().unwrap()) {
-                Ok(all_als_data) => {
-                    projects.push(Project {
-                        name: project_name,
-                        als_data: Some(all_als_data),
-                    });
-                }
-                Err(error) => {
-                    errors.push(error);
-                }
-            }
+            let files =
+                parallel::find_projects(path.to_str().unwrap()).map_err(|e| e.to_string())?;
+
+            // Ableton keeps older versions of each set in the project's backup directory
+            let backup_path = path.join("Backup");
+            let backup_files = if backup_path.is_dir() {
+                parallel::find_projects(backup_path.to_str().unwrap()).map_err(|e| e.to_string())?
+            } else {
+                Vec::new()
+            };
+
+            progress.discovered(files.len() + backup_files.len());
+            found.push(ProjectFiles {
+                name,
+                files,
+                backup_files,
+            });
         }
     }
 
-    if !errors.is_empty() {
-        return Err(errors.join(", "));
-    }
+    Ok(found)
+}
+
+/// Parses a project's sets which are not cached yet, caches them and then loads the whole
+/// project from the cache. Sets which were not parsed yet when `cancelled` is set are skipped
+pub fn load_project(
+    project_files: &ProjectFiles,
+    progress: &Progress,
+    cancelled: &AtomicBool,
+) -> Result<Project, String> {
+    let name = &project_files.name;
 
-    if let Err(e) = cache::cache(projects.clone()) {
+    let backups = parallel::parallel_parse_files(
+        &format!("cache/{}/Backup", name),
+        &project_files.backup_files,
+        progress,
+        cancelled,
+    )?;
+    let all_als_data = parallel::parallel_parse_files(
+        &format!("cache/{}", name),
+        &project_files.files,
+        progress,
+        cancelled,
+    )?;
+
+    if let Err(e) = cache::cache(vec![Project {
+        name: name.clone(),
+        als_data: Some(all_als_data),
+        backups,
+    }]) {
         eprintln!("Error creating cache: {:?}", e);
     }
-    let retrieved = cache::retrieve().map_err(|e| format!("Failed to retrieve cache: {}", e))?;
 
-    Ok(retrieved)
+    cache::retrieve_project(name)
+        .map_err(|e| format!("Failed to retrieve cache of {}: {}", name, e))
+}
+
+/// Extracts and parses a single *als* file, naming it after the file stem
+pub fn parse_als_file(als_path: &Path) -> Result<AlsData, String> {
+    let name = als_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| format!("Invalid als file name: {}", als_path.display()))?
+        .to_string();
+
+    let extracted = extract::extract_pruned(&als_path.to_string_lossy())
+        .map_err(|e| format!("Failed to extract {}: {}", als_path.display(), e))?;
+
+    let mut als_data = AlsData::parse(name, extracted.xml);
+    als_data.salvaged = extracted.salvaged;
+    als_data.set_file(&als_path.to_string_lossy());
+    Ok(als_data)
 }
@@ -1,28 +1,33 @@
 // Used for parallelised iteration to extract and parse multiple files at once
 use rayon::prelude::*;
 
+use crate::cache;
 use crate::extract;
-use crate::parse::als::AlsData;
+use crate::palsa;
+use crate::parse::als::{AlsData, Project};
+use crate::progress::Progress;
 use std::{
     fs,
     path::Path,
-    sync::{Arc, Mutex},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-/// Uses [`rayon`]'s `par_iter` and `parallel`'s `find_projects`
-/// to find all *als* files in a directory then extracts and parses them in parallel
-pub fn parallel_parse_dir(project_name: &str, dir: &str) -> Result<Vec<AlsData>, String> {
-    let projects: Vec<String> = match find_projects(dir) {
-        Ok(files) => files,
-        Err(e) => return Err(e.to_string()),
-    };
-
-    let completed_files = Arc::new(Mutex::new(vec![false; projects.len()]));
-
-    let all_als_data: Result<Vec<AlsData>, String> = projects
+/// Uses [`rayon`]'s `par_iter` to extract and parse *als* files in parallel,
+/// files which already have an up to date *yaml* file in `cache_dir` are skipped
+/// as are files which were not started on yet once `cancelled` is set
+pub fn parallel_parse_files(
+    cache_dir: &str,
+    files: &[String],
+    progress: &Progress,
+    cancelled: &AtomicBool,
+) -> Result<Vec<AlsData>, String> {
+    files
         .par_iter()
-        .enumerate()
-        .filter_map(|(i, als_file)| {
+        .filter_map(|als_file| {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+
             let file_name = Path::new(als_file)
                 .file_stem()
                 .and_then(|stem| stem.to_str())
@@ -30,32 +35,30 @@ pub fn parallel_parse_dir(project_name: &str, dir: &str) -> Result<Vec<AlsData>,
                 .ok()?
                 .to_owned();
 
-            if !Path::new(&format!("cache/{}/{}.yaml", project_name, file_name)).is_file() {
-                match extract::extract(als_file.clone()).map_err(|e| e.to_string()) {
-                    Ok(extracted_xml_contents) => {
-                        let als_data = AlsData::parse(file_name, extracted_xml_contents);
-
-                        // Update the completed files
-                        let mut completed =
-                            completed_files.lock().map_err(|e| e.to_string()).ok()?;
-                        completed[i] = true;
-
-                        Some(Ok(als_data))
-                    }
-                    Err(e) => Some(Err(e)),
-                }
-            } else {
+            if cache::is_cached(cache_dir, &file_name, Path::new(als_file)) {
                 // File is already in cache, do nothing
-                None
+                progress.cache_hit();
+                return None;
             }
-        })
-        .collect();
 
-    all_als_data.map(|data| data.into_iter().filter_map(|als| Some(als)).collect())
+            progress.parsing(&file_name);
+            let als_data = extract::extract_pruned(als_file)
+                .map_err(|e| e.to_string())
+                .map(|extracted| {
+                    let mut als_data = AlsData::parse(file_name, extracted.xml);
+                    als_data.salvaged = extracted.salvaged;
+                    als_data.set_file(als_file);
+                    als_data
+                });
+            progress.parsed();
+
+            Some(als_data)
+        })
+        .collect()
 }
 
 /// Finds all *als* files within a given directory
-fn find_projects(dir: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub fn find_projects(dir: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let mut projects = Vec::new();
     for als_file in fs::read_dir(dir)? {
         let als_file = als_file?;
@@ -67,3 +70,42 @@ fn find_projects(dir: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     }
     Ok(projects)
 }
+
+/// Extracts and parses every *als* file of a single project directory and its backups
+/// in parallel without touching the cache
+pub fn parallel_parse_project(dir: &Path, progress: &Progress) -> Result<Project, String> {
+    let name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| "Invalid project directory name".to_string())?
+        .to_string();
+
+    let find_all = |dir: &Path| -> Result<Vec<String>, String> {
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        find_projects(dir.to_str().unwrap()).map_err(|e| e.to_string())
+    };
+    let parse_all = |files: Vec<String>| -> Result<Vec<AlsData>, String> {
+        files
+            .par_iter()
+            .map(|als_file| {
+                let als_path = Path::new(als_file);
+                progress.parsing(&als_path.file_stem().unwrap_or_default().to_string_lossy());
+                let als_data = palsa::parse_als_file(als_path);
+                progress.parsed();
+                als_data
+            })
+            .collect()
+    };
+
+    let files = find_all(dir)?;
+    let backup_files = find_all(&dir.join("Backup"))?;
+    progress.discovered(files.len() + backup_files.len());
+
+    Ok(Project {
+        name,
+        als_data: Some(parse_all(files)?),
+        backups: parse_all(backup_files)?,
+    })
+}
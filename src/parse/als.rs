@@ -1,45 +1,261 @@
-use crate::parse::{group, track};
-use roxmltree::Document;
+use crate::find_attribute_value;
+use crate::parse::{
+    group,
+    track::{self, TrackKind},
+    version::{LiveVersion, Profile},
+};
+use roxmltree::{Document, Node};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::{fs, time::UNIX_EPOCH};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+// Changing this or the types within it needs `cache::CACHE_VERSION` to be bumped
+/// The range of tempos in beats per minute live can be set to
+pub const TEMPO_RANGE: std::ops::RangeInclusive<f32> = 20.0..=999.0;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct AlsData {
+    /// File name of the set without its extension
     pub name: String,
+    /// Where the *als* file was parsed from
+    #[serde(default)]
+    pub path: String,
+    /// When the *als* file was last saved, in seconds since the unix epoch
+    #[serde(default)]
+    pub modified: u64,
+    /// Size of the *als* file in bytes
+    #[serde(default)]
+    pub file_size: u64,
+    /// In beats per minute
+    #[serde(default = "default_tempo")]
+    pub tempo: f32,
+    #[serde(default)]
+    pub time_signature: TimeSignature,
+    /// The version of live which saved the set
+    #[serde(default)]
+    pub version: LiveVersion,
     pub groups: Vec<group::Group>,
     pub tracks: Vec<track::Track>,
+    /// Whether the file is damaged, so only the tracks and clips which could be salvaged are known
+    #[serde(default)]
+    pub salvaged: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct Project {
     pub name: String,
     pub als_data: Option<Vec<AlsData>>,
+    /// Older versions of the project's sets found in its `Backup` directory
+    pub backups: Vec<AlsData>,
+}
+
+/// The master time signature of a set, clip positions are stored in quarter note beats
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq)]
+pub struct TimeSignature {
+    pub numerator: u32,
+    pub denominator: u32,
 }
 
 impl AlsData {
-    /// Uses all `parse` modules to parse *als* files
+    /// Uses all `parse` modules to parse *als* files,
+    /// following the profile of the version of live which saved the set
     pub fn parse(name: String, xml_contents: String) -> AlsData {
         let doc = Document::parse(&xml_contents).unwrap();
         let root = doc.root_element();
+        let version = LiveVersion::parse(root);
+        let profile = version.profile();
 
-        let tracks: Vec<track::Track> = root
-            .descendants()
-            .filter(|n| {
-                n.has_tag_name("MidiTrack")
-                    || n.has_tag_name("AudioTrack")
-                    || n.has_tag_name("ReturnTrack")
-            })
-            .map(|n| track::Track::parse(n))
-            .collect();
+        let (tempo, time_signature) = parse_master_timing(root, profile);
 
-        let groups: Vec<group::Group> = root
-            .descendants()
-            .filter_map(|n| group::Group::parse(n))
+        let tracks: Vec<track::Track> = track::track_nodes(root, profile)
+            .into_iter()
+            .map(|n| track::Track::parse(n, profile))
             .collect();
 
-        return AlsData {
+        let groups: Vec<group::Group> =
+            root.descendants().filter_map(group::Group::parse).collect();
+
+        AlsData {
             name,
+            path: String::new(),
+            modified: 0,
+            file_size: 0,
+            tempo,
+            time_signature,
+            version,
             groups,
             tracks,
-        };
+            salvaged: false,
+        }
+    }
+
+    /// Records where the set was parsed from along with when that file was last saved and its size
+    pub fn set_file(&mut self, path: &str) {
+        self.path = path.to_string();
+
+        let metadata = fs::metadata(path).ok();
+        self.modified = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+        self.file_size = metadata.map_or(0, |metadata| metadata.len());
+    }
+
+    /// Whether a track is muted or within a muted group, either of which silences it
+    pub fn is_muted(&self, track: &track::Track) -> bool {
+        track.muted || self.enclosing_groups(track).any(|group| group.muted)
+    }
+
+    /// Whether a track is frozen or within a frozen group, either of which freezes it
+    pub fn is_frozen(&self, track: &track::Track) -> bool {
+        track.frozen || self.enclosing_groups(track).any(|group| group.frozen)
+    }
+
+    /// The groups containing a track from the innermost outwards
+    fn enclosing_groups(&self, track: &track::Track) -> impl Iterator<Item = &group::Group> {
+        let mut group_id = track.group_id;
+        // Bounded so groups nesting each other in a damaged set can't loop forever
+        (0..self.groups.len()).map_while(move |_| {
+            let group = self
+                .groups
+                .iter()
+                .find(|group| group_id >= 0 && group.id == group_id as u32)?;
+            group_id = group.group_id;
+            Some(group)
+        })
+    }
+
+    /// The number of tracks not counting the master and prehear tracks, which every set has
+    pub fn track_count(&self) -> usize {
+        self.tracks
+            .iter()
+            .filter(|track| !matches!(track.kind, TrackKind::Master | TrackKind::PreHear))
+            .count()
+    }
+
+    /// The end of the last clip in beats
+    pub fn length(&self) -> f32 {
+        self.tracks
+            .iter()
+            .flat_map(|track| track.clips.iter().map(|clip| clip.end))
+            .fold(0., f32::max)
+    }
+
+    /// The number of bars up to the end of the last clip
+    pub fn length_in_bars(&self) -> u32 {
+        (self.length() / self.time_signature.bar_length()).ceil() as u32
+    }
+}
+
+impl TimeSignature {
+    /// Decodes the master track's time signature value
+    /// which ableton stores as `numerator - 1 + 99 * log2(denominator)`,
+    /// values too large for any denominator fall back to 4/4
+    pub fn from_encoded(value: u32) -> TimeSignature {
+        match 2u32.checked_pow(value / 99) {
+            Some(denominator) => TimeSignature {
+                numerator: value % 99 + 1,
+                denominator,
+            },
+            None => TimeSignature::default(),
+        }
+    }
+
+    /// The length of one bar in quarter note beats
+    pub fn bar_length(&self) -> f32 {
+        self.numerator as f32 * 4. / self.denominator as f32
+    }
+
+    /// Formats a position in the arrangement as `bars:beats`, counting from `1:1` like ableton does
+    pub fn format_position(&self, time: f32) -> String {
+        let (bars, beats) = self.split(time);
+        format_bars_beats(bars + 1., beats + 1.)
+    }
+
+    /// Formats a length as `bars:beats`, counting from `0:0`
+    pub fn format_length(&self, length: f32) -> String {
+        let (bars, beats) = self.split(length);
+        format_bars_beats(bars, beats)
+    }
+
+    /// Splits a time in quarter note beats into whole bars and the beats left over,
+    /// beats are counted in the denominator's note value
+    fn split(&self, time: f32) -> (f32, f32) {
+        let bars = (time / self.bar_length()).floor();
+        let beats = (time - bars * self.bar_length()) * self.denominator as f32 / 4.;
+        (bars, beats)
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature {
+            numerator: 4,
+            denominator: 4,
+        }
+    }
+}
+
+/// Parses the tempo and time signature from the master track of a set's root node
+pub fn parse_master_timing(root: Node, profile: &Profile) -> (f32, TimeSignature) {
+    // Damaged sets may have lost their master track
+    let tempo: f32 = find_attribute_value!(root, profile.main_track, "Tempo", "Manual")
+        .and_then(|tempo| tempo.parse().ok())
+        .unwrap_or_else(default_tempo);
+    let time_signature = find_attribute_value!(root, profile.main_track, "TimeSignature", "Manual")
+        .and_then(|value| value.parse().ok())
+        .map(TimeSignature::from_encoded)
+        .unwrap_or_default();
+
+    (tempo, time_signature)
+}
+
+fn format_bars_beats(bars: f32, beats: f32) -> String {
+    if beats.fract().abs() < 0.001 {
+        format!("{}:{}", bars, beats.round())
+    } else {
+        format!("{}:{:.2}", bars, beats)
+    }
+}
+
+/// Fails for tempos live can not be set to, such as 0 or not a number at all
+pub fn check_tempo(tempo: f32) -> Result<(), String> {
+    if TEMPO_RANGE.contains(&tempo) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid tempo {}, tempos go from {} to {}",
+            tempo,
+            TEMPO_RANGE.start(),
+            TEMPO_RANGE.end()
+        ))
+    }
+}
+
+fn default_tempo() -> f32 {
+    120.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time_signature(numerator: u32, denominator: u32) -> TimeSignature {
+        TimeSignature {
+            numerator,
+            denominator,
+        }
+    }
+
+    #[test]
+    fn decodes_time_signatures() {
+        assert_eq!(TimeSignature::from_encoded(201), time_signature(4, 4));
+        assert_eq!(TimeSignature::from_encoded(302), time_signature(6, 8));
+        assert_eq!(TimeSignature::from_encoded(402), time_signature(7, 16));
+        assert_eq!(
+            TimeSignature::from_encoded(u32::MAX),
+            TimeSignature::default()
+        );
     }
 }
@@ -1,20 +1,43 @@
 use roxmltree::Node;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::get_attribute_value;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Clip {
     pub name: String,
+    #[serde(default)]
+    pub kind: ClipKind,
+    /// Position in the arrangement in quarter note beats
     pub start: f32,
     pub end: f32,
     pub loop_data: Option<Loop>,
+    /// Path of the sample an audio clip plays
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Deactivated clips stay in the arrangement but are not played
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Default)]
+pub enum ClipKind {
+    #[default]
+    Midi,
+    Audio,
 }
 
 impl Clip {
     pub fn parse(node: Node) -> Clip {
         let name: String = get_attribute_value!(node, "Name").to_string();
 
+        let kind = if node.has_tag_name("AudioClip") {
+            ClipKind::Audio
+        } else {
+            ClipKind::Midi
+        };
+
         let start: f32 = get_attribute_value!(node, "CurrentStart")
             .parse()
             .unwrap_or(-1.0);
@@ -30,18 +53,45 @@ impl Clip {
                 .parse()
                 .unwrap(),
             end: get_attribute_value!(loop_node, "LoopEnd").parse().unwrap(),
+            on: get_attribute_value!(loop_node, "LoopOn") == "true",
+            start_relative: get_attribute_value!(loop_node, "StartRelative")
+                .parse()
+                .unwrap_or(0.),
         });
 
+        // Older sets store the sample location without a `Path`, so it is optional
+        let source = node
+            .descendants()
+            .find(|n| n.has_tag_name("SampleRef"))
+            .and_then(|n| n.descendants().find(|n| n.has_tag_name("Path")))
+            .and_then(|n| n.attribute("Value"))
+            .map(|path| path.to_string());
+
+        let disabled = node
+            .children()
+            .find(|n| n.has_tag_name("Disabled"))
+            .and_then(|n| n.attribute("Value"))
+            == Some("true");
+
         Clip {
             name,
+            kind,
             start,
             end,
             loop_data,
+            source,
+            disabled,
         }
     }
 }
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Loop {
+    /// Position within the clip's contents in quarter note beats
     pub start: f64,
     pub end: f64,
+    #[serde(default)]
+    pub on: bool,
+    /// Where playback starts relative to the loop start when looping
+    #[serde(default)]
+    pub start_relative: f64,
 }
@@ -0,0 +1,50 @@
+use roxmltree::Node;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+pub struct Device {
+    pub name: String,
+    /// The device's tag name such as `Eq8` or `PluginDevice`
+    pub kind: String,
+    /// Whether the device is switched on with its activator
+    #[serde(default = "switched_on")]
+    pub on: bool,
+}
+
+impl Device {
+    pub fn parse(node: Node) -> Device {
+        let kind = node.tag_name().name().to_string();
+
+        let user_name = child_value(node, "UserName").filter(|name| !name.is_empty());
+
+        // Plugins store their name in a format specific `PluginInfo` element
+        let plugin_name = node
+            .children()
+            .find(|n| n.has_tag_name("PluginDesc"))
+            .and_then(|n| n.children().find(|n| n.is_element()))
+            .and_then(|info| child_value(info, "PlugName").or_else(|| child_value(info, "Name")));
+
+        let name = user_name.or(plugin_name).unwrap_or_else(|| kind.clone());
+
+        let on = node
+            .children()
+            .find(|n| n.has_tag_name("On"))
+            .and_then(|n| n.children().find(|n| n.has_tag_name("Manual")))
+            .and_then(|n| n.attribute("Value"))
+            != Some("false");
+
+        Device { name, kind, on }
+    }
+}
+
+fn child_value(node: Node, tag_name: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.has_tag_name(tag_name))
+        .and_then(|n| n.attribute("Value"))
+        .map(|value| value.to_string())
+}
+
+fn switched_on() -> bool {
+    true
+}
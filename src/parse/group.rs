@@ -1,13 +1,33 @@
 use roxmltree::Node;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::get_attribute_value;
+use crate::parse::{
+    routing::{Sidechain, TrackRouting},
+    track::{self, TrackSend},
+};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Group {
     pub id: u32,
     pub name: String,
     pub color: Option<u32>,
+    /// Id of the group containing this one, -1 when it is not nested
+    #[serde(default = "no_group")]
+    pub group_id: i32,
+    #[serde(default)]
+    pub sends: Vec<TrackSend>,
+    #[serde(default)]
+    pub routing: TrackRouting,
+    #[serde(default)]
+    pub sidechains: Vec<Sidechain>,
+    /// Whether the group's activator is switched off, silencing every track within it
+    #[serde(default)]
+    pub muted: bool,
+    /// Freezing a group freezes every track within it
+    #[serde(default)]
+    pub frozen: bool,
 }
 
 impl Group {
@@ -21,6 +41,27 @@ impl Group {
 
         let color = None;
 
-        Some(Group { id, name, color })
+        let group_id = node
+            .children()
+            .find(|n| n.has_tag_name("TrackGroupId"))
+            .and_then(|n| n.attribute("Value"))
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(-1);
+
+        Some(Group {
+            id,
+            name,
+            color,
+            group_id,
+            sends: TrackSend::parse_track(node),
+            routing: TrackRouting::parse(node),
+            sidechains: Sidechain::parse_track(node),
+            muted: track::parse_muted(node),
+            frozen: track::parse_frozen(node),
+        })
     }
 }
+
+fn no_group() -> i32 {
+    -1
+}
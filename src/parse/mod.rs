@@ -1,4 +1,8 @@
 pub mod als;
 pub mod clip;
+pub mod device;
 pub mod group;
+pub mod note;
+pub mod routing;
 pub mod track;
+pub mod version;
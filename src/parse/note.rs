@@ -0,0 +1,41 @@
+use roxmltree::Node;
+
+/// A note of a midi clip, not kept in `AlsData` as there can be hundreds of thousands of them
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub key: u8,
+    /// Position within the clip's contents in quarter note beats
+    pub time: f64,
+    pub duration: f64,
+    pub velocity: u8,
+}
+
+impl Note {
+    /// Parses all enabled notes of a `MidiClip` node
+    pub fn parse_clip(node: Node) -> Vec<Note> {
+        node.descendants()
+            .filter(|n| n.has_tag_name("KeyTrack"))
+            .flat_map(|key_track| {
+                let key: u8 = key_track
+                    .children()
+                    .find(|n| n.has_tag_name("MidiKey"))
+                    .and_then(|n| n.attribute("Value"))
+                    .and_then(|key| key.parse().ok())
+                    .unwrap_or(60);
+
+                key_track
+                    .descendants()
+                    .filter(|n| n.has_tag_name("MidiNoteEvent"))
+                    .filter(|n| n.attribute("IsEnabled") != Some("false"))
+                    .filter_map(move |n| {
+                        Some(Note {
+                            key,
+                            time: n.attribute("Time")?.parse().ok()?,
+                            duration: n.attribute("Duration")?.parse().ok()?,
+                            velocity: n.attribute("Velocity")?.parse::<f32>().ok()?.round() as u8,
+                        })
+                    })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use roxmltree::Node;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::parse::device::Device;
+
+/// Where a track's audio or midi comes from or goes to
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+pub struct Routing {
+    /// Live's name for it such as `AudioOut/Master` or `AudioIn/Track.12/TrackOut`
+    pub target: String,
+    /// What live shows for it such as `Ext. In 1/2`
+    pub display: String,
+}
+
+/// What a routing connects a track to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    /// No input or output, or one palsa does not know
+    Nowhere,
+    /// An input or output of the audio or midi interface
+    External,
+    Master,
+    /// The group containing the track
+    Group,
+    /// Another track or group by its id
+    Track(i32),
+}
+
+/// The inputs and outputs chosen in a track's in/out section
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Default)]
+pub struct TrackRouting {
+    pub audio_input: Option<Routing>,
+    pub audio_output: Option<Routing>,
+    pub midi_input: Option<Routing>,
+    pub midi_output: Option<Routing>,
+}
+
+/// A device listening to the audio of another track through its sidechain input
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq)]
+pub struct Sidechain {
+    pub device: String,
+    pub input: Routing,
+}
+
+impl Routing {
+    /// Parses an element holding a `Target` such as `AudioInputRouting`
+    pub fn parse(node: Node) -> Option<Routing> {
+        let value = |tag_name: &str| {
+            node.children()
+                .find(|n| n.has_tag_name(tag_name))
+                .and_then(|n| n.attribute("Value"))
+        };
+
+        let target = value("Target")?.to_string();
+        let display = [value("UpperDisplayString"), value("LowerDisplayString")]
+            .into_iter()
+            .flatten()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(Routing { target, display })
+    }
+
+    pub fn endpoint(&self) -> Endpoint {
+        match self.target.split('/').nth(1) {
+            Some("Master" | "Main") => Endpoint::Master,
+            Some("GroupTrack") => Endpoint::Group,
+            Some(external) if external.starts_with("External") => Endpoint::External,
+            Some(track) => track
+                .strip_prefix("Track.")
+                .and_then(|id| id.parse().ok())
+                .map_or(Endpoint::Nowhere, Endpoint::Track),
+            None => Endpoint::Nowhere,
+        }
+    }
+}
+
+impl TrackRouting {
+    /// Parses the routing of a track or group node
+    pub fn parse(track: Node) -> TrackRouting {
+        let device_chain = track.children().find(|n| n.has_tag_name("DeviceChain"));
+        let routing = |tag_name: &str| {
+            device_chain
+                .and_then(|n| n.children().find(|n| n.has_tag_name(tag_name)))
+                .and_then(Routing::parse)
+        };
+
+        TrackRouting {
+            audio_input: routing("AudioInputRouting"),
+            audio_output: routing("AudioOutputRouting"),
+            midi_input: routing("MidiInputRouting"),
+            midi_output: routing("MidiOutputRouting"),
+        }
+    }
+}
+
+impl Sidechain {
+    /// The switched on sidechains of the devices in a track's device chain,
+    /// devices nested inside racks are pruned while extracting so they are left out
+    pub fn parse_track(track: Node) -> Vec<Sidechain> {
+        track
+            .children()
+            .find(|n| n.has_tag_name("DeviceChain"))
+            .into_iter()
+            .flat_map(|n| n.descendants())
+            .filter(|n| n.has_tag_name("SideChain"))
+            .filter_map(|n| {
+                let on = n
+                    .children()
+                    .find(|n| n.has_tag_name("OnOff"))
+                    .and_then(|n| n.children().find(|n| n.has_tag_name("Manual")))
+                    .and_then(|n| n.attribute("Value"));
+                let input = n
+                    .children()
+                    .find(|n| n.has_tag_name("RoutedInput"))
+                    .and_then(|n| n.children().find(|n| n.has_tag_name("Routable")))
+                    .and_then(Routing::parse)
+                    .filter(|input| input.endpoint() != Endpoint::Nowhere)?;
+
+                if on != Some("true") {
+                    return None;
+                }
+                Some(Sidechain {
+                    device: Device::parse(n.parent_element()?).name,
+                    input,
+                })
+            })
+            .collect()
+    }
+}
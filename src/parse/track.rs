@@ -1,36 +1,299 @@
-use crate::parse::clip;
+use crate::parse::{
+    clip, device,
+    routing::{Sidechain, TrackRouting},
+    version::Profile,
+};
 use roxmltree::Node;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::get_attribute_value;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct Track {
+    /// Stays the same when a track is renamed or moved, -1 for the master and prehear tracks
+    /// and for tracks cached before ids were parsed
+    #[serde(default = "no_id")]
+    pub id: i32,
+    #[serde(default)]
+    pub kind: TrackKind,
+    /// Id of the group containing the track, -1 when it is not grouped
     pub group_id: i32,
     pub name: String,
+    /// Index into ableton's palette of 70 colors
     pub color: Option<usize>,
     pub clips: Vec<clip::Clip>,
+    #[serde(default)]
+    pub devices: Vec<device::Device>,
+    /// Recordings kept on the track which can be comped into its clips
+    #[serde(default)]
+    pub take_lanes: Vec<TakeLane>,
+    /// How much of the track is sent to each of the set's return tracks, in their order
+    #[serde(default)]
+    pub sends: Vec<TrackSend>,
+    #[serde(default)]
+    pub routing: TrackRouting,
+    #[serde(default)]
+    pub sidechains: Vec<Sidechain>,
+    /// Whether the track's activator is switched off, silencing it
+    #[serde(default)]
+    pub muted: bool,
+    /// Frozen tracks play a render of their clips and devices, which can't be edited until unfrozen
+    #[serde(default)]
+    pub frozen: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Default)]
+pub enum TrackKind {
+    #[default]
+    Midi,
+    Audio,
+    Return,
+    /// Called the main track from live 12 onwards
+    Master,
+    /// The track live previews samples from the browser on, it is never shown in the arrangement
+    PreHear,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq)]
+pub struct TrackSend {
+    /// Linear gain from 0.0003 (-70 dB, which live shows as -inf) to 1 (0 dB)
+    pub level: f32,
+    /// Sends which are switched off do not send anything whatever their level
+    pub active: bool,
+}
+
+/// A lane of takes recorded on a track, the parts comped from it are copied into the track's clips
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
+pub struct TakeLane {
+    pub name: String,
+    pub clips: Vec<clip::Clip>,
+    /// Whether the lane is being auditioned, playing instead of the track's clips
+    #[serde(default)]
+    pub audition: bool,
+    /// The start and end in beats of each part of the lane which is comped into the track's clips,
+    /// only known for audio takes as the notes midi takes are matched by are not parsed
+    #[serde(default)]
+    pub comped: Vec<(f32, f32)>,
 }
 
 impl Track {
-    pub fn parse(node: Node) -> Track {
+    pub fn parse(node: Node, profile: &Profile) -> Track {
+        let kind = TrackKind::from_tag(node.tag_name().name(), profile).unwrap_or_default();
+        let id: i32 = node
+            .attribute("Id")
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(-1);
         let group_id: i32 = get_attribute_value!(node, "TrackGroupId")
             .parse()
             .unwrap_or(-1);
         let name = get_attribute_value!(node, "Name", "EffectiveName").to_string();
-        let color = Some(get_attribute_value!(node, "Color").parse().unwrap());
+        // The prehear track has no colour of -1
+        let color = get_attribute_value!(node, profile.color).parse().ok();
 
-        let clips = node
-            .descendants()
-            .filter(|n| n.has_tag_name("MidiClip") || n.has_tag_name("AudioClip"))
-            .map(|n| clip::Clip::parse(n))
-            .collect();
+        let clips: Vec<clip::Clip> = clip_nodes(node, profile).map(clip::Clip::parse).collect();
+
+        // Only the devices on the track itself, not the ones nested inside racks
+        let devices = node
+            .children()
+            .find(|n| n.has_tag_name("DeviceChain"))
+            .and_then(|n| n.children().find(|n| n.has_tag_name("DeviceChain")))
+            .and_then(|n| n.children().find(|n| n.has_tag_name("Devices")))
+            .map(|n| {
+                n.children()
+                    .filter(|n| n.is_element())
+                    .map(device::Device::parse)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let muted = parse_muted(node);
+        let frozen = parse_frozen(node);
+
+        let take_lanes = if profile.take_lanes {
+            node.children()
+                .find(|n| n.has_tag_name("TakeLanes"))
+                .and_then(|n| n.children().find(|n| n.has_tag_name("TakeLanes")))
+                .map(|n| {
+                    n.children()
+                        .filter(|n| n.has_tag_name("TakeLane"))
+                        .enumerate()
+                        .map(|(i, n)| TakeLane::parse(n, i, &clips))
+                        .collect()
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
 
         Track {
+            id,
+            kind,
             group_id,
             name,
             color,
             clips,
+            devices,
+            take_lanes,
+            sends: TrackSend::parse_track(node),
+            routing: TrackRouting::parse(node),
+            sidechains: Sidechain::parse_track(node),
+            muted,
+            frozen,
+        }
+    }
+}
+
+impl TrackKind {
+    /// The kind of track a tag holds, `None` for tags which are not tracks
+    pub fn from_tag(tag_name: &str, profile: &Profile) -> Option<TrackKind> {
+        match tag_name {
+            "MidiTrack" => Some(TrackKind::Midi),
+            "AudioTrack" => Some(TrackKind::Audio),
+            "ReturnTrack" => Some(TrackKind::Return),
+            "PreHearTrack" => Some(TrackKind::PreHear),
+            tag_name if tag_name == profile.main_track => Some(TrackKind::Master),
+            _ => None,
+        }
+    }
+
+    /// Only midi and audio tracks hold clips, live shows the other tracks below them
+    pub fn holds_clips(&self) -> bool {
+        matches!(self, TrackKind::Midi | TrackKind::Audio)
+    }
+}
+
+impl TrackSend {
+    /// The sends in the mixer of a track or group node
+    pub fn parse_track(track: Node) -> Vec<TrackSend> {
+        track
+            .children()
+            .find(|n| n.has_tag_name("DeviceChain"))
+            .and_then(|n| n.children().find(|n| n.has_tag_name("Mixer")))
+            .and_then(|n| n.children().find(|n| n.has_tag_name("Sends")))
+            .map(|n| {
+                n.children()
+                    .filter(|n| n.has_tag_name("TrackSendHolder"))
+                    .map(TrackSend::parse)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn parse(node: Node) -> TrackSend {
+        let value = |path: &[&str]| {
+            path.iter()
+                .try_fold(node, |node, tag_name| {
+                    node.children().find(|n| n.has_tag_name(*tag_name))
+                })
+                .and_then(|n| n.attribute("Value"))
+        };
+
+        TrackSend {
+            level: value(&["Send", "Manual"])
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(0.),
+            active: value(&["Active"]) != Some("false"),
         }
     }
+
+    /// The level in decibels, `None` when it is all the way down
+    pub fn decibels(&self) -> Option<f32> {
+        let decibels = 20. * self.level.log10();
+        (decibels > -69.9).then_some(decibels)
+    }
+}
+
+impl TakeLane {
+    /// Parses the `index`th take lane of a track whose comped clips are `track_clips`
+    pub fn parse(node: Node, index: usize, track_clips: &[clip::Clip]) -> TakeLane {
+        let name = node
+            .children()
+            .find(|n| n.has_tag_name("Name"))
+            .and_then(|n| n.attribute("Value"))
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| format!("Take {}", index + 1), |name| name.to_string());
+        let audition = node
+            .children()
+            .find(|n| n.has_tag_name("Audition"))
+            .and_then(|n| n.attribute("Value"))
+            == Some("true");
+
+        let clips: Vec<clip::Clip> = node
+            .descendants()
+            .filter(|n| n.has_tag_name("MidiClip") || n.has_tag_name("AudioClip"))
+            .map(clip::Clip::parse)
+            .collect();
+
+        // Comping copies part of a take into the track where it was recorded,
+        // so a track clip of the same recording overlapping a take was comped from it
+        let comped = clips
+            .iter()
+            .flat_map(|take| {
+                track_clips
+                    .iter()
+                    .filter(move |clip| is_same_recording(take, clip))
+                    .map(move |clip| (take.start.max(clip.start), take.end.min(clip.end)))
+                    .filter(|(start, end)| start < end)
+            })
+            .collect();
+
+        TakeLane {
+            name,
+            clips,
+            audition,
+            comped,
+        }
+    }
+}
+
+/// Whether two audio clips play the same sample, midi clips would have to be compared by their
+/// notes which are pruned while extracting, their names alone match unrelated takes
+fn is_same_recording(a: &clip::Clip, b: &clip::Clip) -> bool {
+    a.kind == clip::ClipKind::Audio
+        && b.kind == clip::ClipKind::Audio
+        && a.source.is_some()
+        && a.source == b.source
+}
+
+/// The nodes of every track of a set in the order they are parsed, which is the order live shows
+/// them in with the return tracks and then the master and prehear tracks after the others
+pub fn track_nodes<'a, 'input>(root: Node<'a, 'input>, profile: &Profile) -> Vec<Node<'a, 'input>> {
+    root.descendants()
+        .filter(|n| TrackKind::from_tag(n.tag_name().name(), profile).is_some())
+        .collect()
+}
+
+/// The clip nodes of a track in the order they are parsed,
+/// clips recorded into take lanes are left out as the arrangement does not play them
+pub fn clip_nodes<'a, 'input>(
+    node: Node<'a, 'input>,
+    profile: &Profile,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    let take_lanes = profile.take_lanes;
+    node.descendants()
+        .filter(|n| n.has_tag_name("MidiClip") || n.has_tag_name("AudioClip"))
+        .filter(move |n| !take_lanes || !n.ancestors().any(|n| n.has_tag_name("TakeLanes")))
+}
+
+/// Whether the activator of a track or group is switched off
+pub fn parse_muted(node: Node) -> bool {
+    node.children()
+        .find(|n| n.has_tag_name("DeviceChain"))
+        .and_then(|n| n.children().find(|n| n.has_tag_name("Mixer")))
+        .and_then(|n| n.children().find(|n| n.has_tag_name("Speaker")))
+        .and_then(|n| n.children().find(|n| n.has_tag_name("Manual")))
+        .and_then(|n| n.attribute("Value"))
+        == Some("false")
+}
+
+pub fn parse_frozen(node: Node) -> bool {
+    node.children()
+        .find(|n| n.has_tag_name("Freeze"))
+        .and_then(|n| n.attribute("Value"))
+        == Some("true")
+}
+
+fn no_id() -> i32 {
+    -1
 }
@@ -0,0 +1,205 @@
+use roxmltree::Node;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt};
+
+/// Which version of live saved a set, read from the attributes of its root `Ableton` element
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, PartialEq, Default)]
+pub struct LiveVersion {
+    /// `MajorVersion`, the version of the file format which is 5 from live 10 onwards
+    pub major: u32,
+    /// `MinorVersion` such as `11.0_11202`, starting with the release of live
+    pub minor: String,
+    /// `Creator` such as `Ableton Live 11.2.10`
+    pub creator: String,
+}
+
+/// The tag names and structure which differ between releases of live, see `LiveVersion::profile`
+#[derive(Debug, PartialEq)]
+pub struct Profile {
+    /// The track holding the tempo and time signature, renamed from `MasterTrack` in live 12
+    pub main_track: &'static str,
+    /// The palette index of tracks and clips, which was `ColorIndex` before live 11
+    pub color: &'static str,
+    /// Whether tracks can have take lanes, added in live 11, whose clips are not played in the arrangement
+    pub take_lanes: bool,
+}
+
+/// Live 10 and earlier
+pub const LEGACY: Profile = Profile {
+    main_track: "MasterTrack",
+    color: "ColorIndex",
+    take_lanes: false,
+};
+
+pub const LIVE_11: Profile = Profile {
+    main_track: "MasterTrack",
+    color: "Color",
+    take_lanes: true,
+};
+
+pub const LIVE_12: Profile = Profile {
+    main_track: "MainTrack",
+    color: "Color",
+    take_lanes: true,
+};
+
+impl LiveVersion {
+    pub fn parse(root: Node) -> LiveVersion {
+        LiveVersion {
+            major: root
+                .attribute("MajorVersion")
+                .and_then(|major| major.parse().ok())
+                .unwrap_or(0),
+            minor: root
+                .attribute("MinorVersion")
+                .unwrap_or_default()
+                .to_string(),
+            creator: root.attribute("Creator").unwrap_or_default().to_string(),
+        }
+    }
+
+    /// The release of live such as 11, 0 when it is not known
+    pub fn release(&self) -> u32 {
+        leading_number(&self.minor)
+            .or_else(|| self.numbers().first().copied())
+            .unwrap_or(0)
+    }
+
+    /// How the set is parsed, sets of an unknown version are parsed like live 11 sets
+    pub fn profile(&self) -> &'static Profile {
+        match self.release() {
+            0 | 11 => &LIVE_11,
+            ..=10 => &LEGACY,
+            _ => &LIVE_12,
+        }
+    }
+
+    /// The numbers of the release which saved the set, such as `[11, 2, 10]`
+    fn numbers(&self) -> Vec<u32> {
+        self.creator
+            .strip_prefix("Ableton Live ")
+            .unwrap_or_default()
+            .split(|c: char| !c.is_ascii_digit())
+            .map_while(|number| number.parse().ok())
+            .collect()
+    }
+
+    /// Orders sets from the oldest release of live to the newest
+    pub fn compare(&self, other: &LiveVersion) -> Ordering {
+        self.release()
+            .cmp(&other.release())
+            .then_with(|| self.numbers().cmp(&other.numbers()))
+    }
+}
+
+impl fmt::Display for LiveVersion {
+    /// Such as `11.2.10`, or only the release when the set does not say which update saved it
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.creator.strip_prefix("Ableton Live ") {
+            Some(version) => write!(f, "{}", version),
+            None if self.release() > 0 => write!(f, "{}", self.release()),
+            None => write!(f, "Unknown"),
+        }
+    }
+}
+
+fn leading_number(text: &str) -> Option<u32> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{als::AlsData, track::TrackKind};
+
+    fn version(minor: &str, creator: &str) -> LiveVersion {
+        LiveVersion {
+            major: 5,
+            minor: minor.to_string(),
+            creator: creator.to_string(),
+        }
+    }
+
+    /// A set with a midi track of colour 5 and a master track with a tempo of 128,
+    /// tagged the way `profile` expects
+    fn minimal_set(minor: &str, creator: &str, profile: &Profile) -> String {
+        let track = |tag: &str, id: i32, name: &str| {
+            format!(
+                r#"<{tag} Id="{id}"><Name><EffectiveName Value="{name}" /></Name>
+                <{color} Value="5" /><TrackGroupId Value="-1" />
+                <Tempo><Manual Value="128" /></Tempo></{tag}>"#,
+                color = profile.color,
+            )
+        };
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <Ableton MajorVersion="5" MinorVersion="{}" Creator="{}"><LiveSet><Tracks>{}</Tracks>{}</LiveSet></Ableton>"#,
+            minor,
+            creator,
+            track("MidiTrack", 8, "Keys"),
+            track(profile.main_track, -1, "Main"),
+        )
+    }
+
+    #[test]
+    fn release_and_profile() {
+        for (minor, creator, release, profile) in [
+            ("9.7_178", "Ableton Live 9.7.7", 9, &LEGACY),
+            ("10.0_377", "Ableton Live 10.1.30", 10, &LEGACY),
+            ("11.0_11202", "Ableton Live 11.2.10", 11, &LIVE_11),
+            ("12.0_12049", "Ableton Live 12.0.5", 12, &LIVE_12),
+            // Only the creator says which release saved the set
+            ("", "Ableton Live 10.1.30", 10, &LEGACY),
+            ("", "Ableton Live 12.1", 12, &LIVE_12),
+        ] {
+            let version = version(minor, creator);
+            assert_eq!(version.release(), release, "{} {}", minor, creator);
+            assert_eq!(version.profile(), profile, "{} {}", minor, creator);
+        }
+    }
+
+    #[test]
+    fn missing_version_is_parsed_like_live_11() {
+        for version in [LiveVersion::default(), version("", "Some other program")] {
+            assert_eq!(version.release(), 0);
+            assert_eq!(version.profile(), &LIVE_11);
+            assert_eq!(version.to_string(), "Unknown");
+        }
+    }
+
+    #[test]
+    fn parses_each_profile() {
+        for (minor, creator, profile) in [
+            ("9.7_178", "Ableton Live 9.7.7", &LEGACY),
+            ("10.0_377", "Ableton Live 10.1.30", &LEGACY),
+            ("11.0_11202", "Ableton Live 11.2.10", &LIVE_11),
+            ("12.0_12049", "Ableton Live 12.0.5", &LIVE_12),
+        ] {
+            let als_data = AlsData::parse("Song".to_string(), minimal_set(minor, creator, profile));
+            assert_eq!(als_data.version.profile(), profile, "{}", creator);
+            assert_eq!(als_data.tempo, 128., "{}", creator);
+
+            let kinds: Vec<TrackKind> = als_data.tracks.iter().map(|t| t.kind).collect();
+            assert_eq!(kinds, [TrackKind::Midi, TrackKind::Master], "{}", creator);
+            assert!(
+                als_data.tracks.iter().all(|t| t.color == Some(5)),
+                "{}",
+                creator
+            );
+        }
+    }
+
+    #[test]
+    fn live_12_has_no_master_track() {
+        let als_data = AlsData::parse(
+            "Song".to_string(),
+            minimal_set("12.0_12049", "Ableton Live 12.0.5", &LIVE_11),
+        );
+        assert_eq!(als_data.tempo, 120.);
+        assert_eq!(als_data.tracks.len(), 1);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+
+/// How far loading sets has come
+#[derive(Debug, Clone, Default)]
+pub struct ProgressReport {
+    /// Number of *als* files found which will be loaded
+    pub discovered: usize,
+    /// Number of sets which were extracted and parsed, including ones which failed
+    pub parsed: usize,
+    /// Number of sets which were up to date in the cache and did not need parsing
+    pub cache_hits: usize,
+    /// The set parsed most recently
+    pub current: Option<String>,
+}
+
+impl ProgressReport {
+    pub fn done(&self) -> usize {
+        self.parsed + self.cache_hits
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done() >= self.discovered
+    }
+
+    /// Between 0 and 1
+    pub fn fraction(&self) -> f32 {
+        if self.discovered == 0 {
+            1.
+        } else {
+            self.done() as f32 / self.discovered as f32
+        }
+    }
+}
+
+impl std::fmt::Display for ProgressReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Loaded {} of {} sets ({} from cache)",
+            self.done(),
+            self.discovered,
+            self.cache_hits
+        )?;
+        if let Some(ref current) = self.current {
+            write!(f, ", {}", current)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tracks loading progress across the threads parsing sets,
+/// calling its listener with the new report after every event
+pub struct Progress {
+    report: Mutex<ProgressReport>,
+    listener: Box<dyn Fn(&ProgressReport) + Send + Sync>,
+}
+
+impl Progress {
+    pub fn new(listener: impl Fn(&ProgressReport) + Send + Sync + 'static) -> Progress {
+        Progress {
+            report: Mutex::new(ProgressReport::default()),
+            listener: Box::new(listener),
+        }
+    }
+
+    /// Progress which nobody listens to
+    pub fn silent() -> Progress {
+        Progress::new(|_| {})
+    }
+
+    pub fn discovered(&self, count: usize) {
+        self.update(|report| report.discovered += count);
+    }
+
+    pub fn parsing(&self, name: &str) {
+        self.update(|report| report.current = Some(name.to_string()));
+    }
+
+    pub fn parsed(&self) {
+        self.update(|report| report.parsed += 1);
+    }
+
+    pub fn cache_hit(&self) {
+        self.update(|report| report.cache_hits += 1);
+    }
+
+    fn update(&self, change: impl FnOnce(&mut ProgressReport)) {
+        // A thread panicking while parsing should not stop the others from reporting
+        let mut report = self
+            .report
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        change(&mut report);
+        (self.listener)(&report);
+    }
+}
@@ -0,0 +1,357 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use eframe::egui;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use std::{fmt::Write, path::Path};
+
+use crate::colors;
+use crate::parse::{
+    als::AlsData,
+    track::{Track, TrackKind},
+};
+
+const BACKGROUND: [u8; 3] = [27, 27, 27];
+const RULER_TEXT: [u8; 3] = [180, 180, 180];
+const BAR_LINE: [u8; 3] = [60, 60, 60];
+const TRACK_NAME: [u8; 3] = [0, 0, 255];
+/// Deactivated clips are grey like in live
+const DISABLED_CLIP: [u8; 3] = [90, 90, 90];
+/// Width of the track colour stripe on thumbnails
+const THUMBNAIL_STRIPE: u32 = 3;
+
+/// Sizes used to lay out the arrangement overview, in pixels
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    pub pixels_per_beat: f32,
+    pub track_height: f32,
+    pub ruler_height: f32,
+    /// Draw the track names and bar numbers, thumbnails are too small for them
+    pub labels: bool,
+}
+
+impl Default for RenderOptions {
+    /// Matches the default zoom of the gui's preview
+    fn default() -> Self {
+        RenderOptions {
+            pixels_per_beat: 3.,
+            track_height: 13.,
+            ruler_height: 16.,
+            labels: true,
+        }
+    }
+}
+
+/// Renders a small overview without labels, stretched to exactly `size`
+pub fn thumbnail(als_data: &AlsData, size: [u32; 2]) -> RgbaImage {
+    let length = als_data.length();
+    let tracks = shown_tracks(als_data);
+
+    // Drawn at least a pixel per track and four per beat so thin clips do not vanish when scaled
+    let options = RenderOptions {
+        pixels_per_beat: (size[0] as f32 / length.max(1.)).max(4.),
+        track_height: (size[1] as f32 / tracks.len().max(1) as f32).max(1.),
+        ruler_height: 0.,
+        labels: false,
+    };
+
+    let mut image = imageops::resize(
+        &Overview::new(als_data, options).to_image(),
+        size[0],
+        size[1],
+        imageops::FilterType::Triangle,
+    );
+
+    // A stripe of each track's colour along the left edge, like the track headers in live
+    let palette = colors::palette();
+    let track_height = size[1] as f32 / tracks.len().max(1) as f32;
+    for (i, track) in tracks.iter().enumerate() {
+        let [r, g, b] = track_color(track.color, &palette);
+        let top = (i as f32 * track_height) as u32;
+        let bottom = (((i + 1) as f32 * track_height) as u32).clamp(top + 1, size[1]);
+        for y in top..bottom {
+            for x in 0..THUMBNAIL_STRIPE.min(size[0]) {
+                image.put_pixel(x, y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+    image
+}
+
+/// The tracks live shows in the arrangement, which is all of them but the prehear track
+fn shown_tracks(als_data: &AlsData) -> Vec<&Track> {
+    als_data
+        .tracks
+        .iter()
+        .filter(|track| track.kind != TrackKind::PreHear)
+        .collect()
+}
+
+fn track_color(color: Option<usize>, palette: &[String]) -> [u8; 3] {
+    color
+        .and_then(|color| palette.get(color))
+        .map(|hex| colors::hex_to_rgb(hex))
+        .unwrap_or([255, 0, 255])
+}
+
+/// Something to draw, shared by the *svg* and *png* renderers so both look the same
+enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [u8; 3],
+    },
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        size: f32,
+        color: [u8; 3],
+    },
+}
+
+/// The arrangement overview of a set, drawn like the gui's preview with a bar ruler on top
+pub struct Overview {
+    width: u32,
+    height: u32,
+    shapes: Vec<Shape>,
+}
+
+impl Overview {
+    pub fn new(als_data: &AlsData, options: RenderOptions) -> Overview {
+        let palette = colors::palette();
+        let ruler_height = if options.labels {
+            options.ruler_height
+        } else {
+            0.
+        };
+
+        let tracks = shown_tracks(als_data);
+        let length = als_data.length();
+        let width = (length * options.pixels_per_beat).ceil().max(1.);
+        let height = ruler_height + tracks.len() as f32 * options.track_height;
+
+        let mut shapes = vec![Shape::Rect {
+            x: 0.,
+            y: 0.,
+            width,
+            height: height.max(1.),
+            color: BACKGROUND,
+        }];
+
+        // Bar lines are spaced at least 40 pixels apart so their numbers do not overlap
+        let bar_width = als_data.time_signature.bar_length() * options.pixels_per_beat;
+        let bar_step = (40. / bar_width).ceil().max(1.) as usize;
+        let bars = als_data.length_in_bars() as usize;
+        for bar in (0..=bars).step_by(bar_step) {
+            let x = bar as f32 * bar_width;
+            shapes.push(Shape::Rect {
+                x,
+                y: 0.,
+                width: 1.,
+                height,
+                color: BAR_LINE,
+            });
+            if options.labels {
+                shapes.push(Shape::Text {
+                    x: x + 2.,
+                    y: 0.,
+                    text: (bar + 1).to_string(),
+                    size: ruler_height * 0.75,
+                    color: RULER_TEXT,
+                });
+            }
+        }
+
+        for (i, track) in tracks.iter().enumerate() {
+            let y = ruler_height + i as f32 * options.track_height;
+            let color = track_color(track.color, &palette);
+
+            for clip in &track.clips {
+                shapes.push(Shape::Rect {
+                    x: clip.start * options.pixels_per_beat,
+                    y,
+                    width: (clip.end - clip.start) * options.pixels_per_beat,
+                    height: options.track_height * 0.98,
+                    color: if clip.disabled {
+                        DISABLED_CLIP
+                    } else if als_data.is_muted(track) {
+                        faded(color)
+                    } else {
+                        color
+                    },
+                });
+            }
+            if options.labels {
+                shapes.push(Shape::Text {
+                    x: 0.,
+                    y,
+                    text: track.name.clone(),
+                    size: 10.,
+                    color: TRACK_NAME,
+                });
+            }
+        }
+
+        Overview {
+            width: width as u32,
+            height: height.ceil().max(1.) as u32,
+            shapes,
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            self.width, self.height
+        );
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    x,
+                    y,
+                    width,
+                    height,
+                    svg_color(*color)
+                ),
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    size,
+                    color,
+                } => writeln!(
+                    svg,
+                    "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" dominant-baseline=\"hanging\" fill=\"{}\">{}</text>",
+                    x,
+                    y,
+                    size,
+                    svg_color(*color),
+                    escape_xml(text)
+                ),
+            }
+            .unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+
+        // The monospace font bundled with egui, so no system fonts are needed
+        let font_data = egui::FontDefinitions::default().font_data["Hack"]
+            .font
+            .clone();
+        let font = FontRef::try_from_slice(&font_data).expect("Failed to load the bundled font");
+
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => {
+                    let x_range = x.round().max(0.) as u32..(x + width).round() as u32;
+                    for py in y.round().max(0.) as u32..(y + height).round() as u32 {
+                        for px in x_range.clone() {
+                            if px < self.width && py < self.height {
+                                image.put_pixel(px, py, Rgba([color[0], color[1], color[2], 255]));
+                            }
+                        }
+                    }
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    text,
+                    size,
+                    color,
+                } => draw_text(&mut image, &font, (*x, *y), text, *size, *color),
+            }
+        }
+        image
+    }
+
+    /// Writes the overview as an *svg* or *png* depending on the file extension
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let result = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("svg") => std::fs::write(path, self.to_svg()).map_err(|e| e.to_string()),
+            Some("png") => self
+                .to_image()
+                .save_with_format(path, ImageFormat::Png)
+                .map_err(|e| e.to_string()),
+            _ => Err("Only svg and png files can be rendered".to_string()),
+        };
+        result.map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Draws a line of text with its top left corner at `position`, blending it over the image
+fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontRef,
+    position: (f32, f32),
+    text: &str,
+    size: f32,
+    color: [u8; 3],
+) {
+    let font = font.as_scaled(PxScale::from(size));
+    let mut x = position.0;
+
+    for character in text.chars() {
+        let glyph = font
+            .glyph_id(character)
+            .with_scale_and_position(size, ab_glyph::point(x, position.1 + font.ascent()));
+        x += font.h_advance(glyph.id);
+
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                    return;
+                }
+
+                let pixel = image.get_pixel_mut(px as u32, py as u32);
+                for channel in 0..3 {
+                    pixel[channel] = (pixel[channel] as f32 * (1. - coverage)
+                        + color[channel] as f32 * coverage)
+                        .round() as u8;
+                }
+            });
+        }
+    }
+}
+
+/// Mixes a colour into the background, as the clips of muted tracks are drawn
+fn faded(color: [u8; 3]) -> [u8; 3] {
+    let mut faded = BACKGROUND;
+    for channel in 0..3 {
+        faded[channel] =
+            (BACKGROUND[channel] as f32 * 0.65 + color[channel] as f32 * 0.35).round() as u8;
+    }
+    faded
+}
+
+fn svg_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
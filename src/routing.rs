@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use crate::parse::{
+    als::AlsData,
+    routing::{Endpoint, Routing, Sidechain, TrackRouting},
+    track::{TrackKind, TrackSend},
+};
+
+/// The tracks and groups of a set along with how audio and midi flows between them
+#[derive(Debug, Clone, Default)]
+pub struct RoutingGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<Edge>,
+    /// How many columns the nodes are laid out in
+    pub columns: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub name: String,
+    /// Index into ableton's palette of 70 colors
+    pub color: Option<usize>,
+    /// Tracks are on the left, followed by groups with the outermost ones last,
+    /// the return tracks and then the master track
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    Audio,
+    Midi,
+    Send,
+    Sidechain,
+}
+
+/// A connection from the node at index `from` to the node at index `to`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    /// Such as the level of a send or the device listening to a sidechain
+    pub label: String,
+}
+
+/// The parts of a track or group which connect it to others
+struct Connections<'a> {
+    group_id: i32,
+    routing: &'a TrackRouting,
+    sends: &'a [TrackSend],
+    sidechains: &'a [Sidechain],
+}
+
+impl RoutingGraph {
+    pub fn new(als_data: &AlsData) -> RoutingGraph {
+        let mut graph = RoutingGraph::default();
+        // Nodes by the id routings refer to them with
+        let mut by_id: HashMap<i32, usize> = HashMap::new();
+        let mut master = None;
+        let mut returns = Vec::new();
+        let mut connections = Vec::new();
+
+        // Nested groups are drawn left of the groups containing them
+        let depth = |mut group_id: i32| {
+            let mut depth = 0;
+            while let Some(group) = als_data.groups.iter().find(|g| g.id as i32 == group_id) {
+                depth += 1;
+                group_id = group.group_id;
+                if depth > als_data.groups.len() {
+                    break;
+                }
+            }
+            depth
+        };
+        let group_columns = als_data
+            .groups
+            .iter()
+            .map(|group| depth(group.group_id) + 1)
+            .max()
+            .unwrap_or(0);
+
+        for group in &als_data.groups {
+            by_id.insert(group.id as i32, graph.nodes.len());
+            graph.nodes.push(GraphNode {
+                name: group.name.clone(),
+                color: group.color.map(|color| color as usize),
+                column: group_columns - depth(group.group_id),
+            });
+            connections.push(Connections {
+                group_id: group.group_id,
+                routing: &group.routing,
+                sends: &group.sends,
+                sidechains: &group.sidechains,
+            });
+        }
+
+        for track in &als_data.tracks {
+            let column = match track.kind {
+                TrackKind::Midi | TrackKind::Audio => 0,
+                TrackKind::Return => group_columns + 1,
+                TrackKind::Master => group_columns + 2,
+                TrackKind::PreHear => continue,
+            };
+            match track.kind {
+                TrackKind::Return => returns.push(graph.nodes.len()),
+                TrackKind::Master => master = Some(graph.nodes.len()),
+                _ => {}
+            }
+            if track.id >= 0 {
+                by_id.insert(track.id, graph.nodes.len());
+            }
+            graph.nodes.push(GraphNode {
+                name: track.name.clone(),
+                color: track.color,
+                column,
+            });
+            connections.push(Connections {
+                group_id: track.group_id,
+                routing: &track.routing,
+                sends: &track.sends,
+                sidechains: &track.sidechains,
+            });
+        }
+        graph.columns = group_columns + 3;
+
+        let node_of = |endpoint: Endpoint, group_id: i32| match endpoint {
+            Endpoint::Master => master,
+            Endpoint::Group => by_id.get(&group_id).copied(),
+            Endpoint::Track(id) => by_id.get(&id).copied(),
+            Endpoint::Nowhere | Endpoint::External => None,
+        };
+
+        for (node, connection) in connections.iter().enumerate() {
+            let mut add = |from: Option<usize>, to: Option<usize>, kind, label: String| {
+                if let (Some(from), Some(to)) = (from, to) {
+                    graph.edges.push(Edge {
+                        from,
+                        to,
+                        kind,
+                        label,
+                    });
+                }
+            };
+            let routed = |routing: &Option<Routing>| {
+                routing
+                    .as_ref()
+                    .and_then(|routing| node_of(routing.endpoint(), connection.group_id))
+            };
+            let routing = connection.routing;
+
+            add(
+                Some(node),
+                routed(&routing.audio_output),
+                EdgeKind::Audio,
+                String::new(),
+            );
+            add(
+                routed(&routing.audio_input),
+                Some(node),
+                EdgeKind::Audio,
+                String::new(),
+            );
+            add(
+                Some(node),
+                routed(&routing.midi_output),
+                EdgeKind::Midi,
+                String::new(),
+            );
+            add(
+                routed(&routing.midi_input),
+                Some(node),
+                EdgeKind::Midi,
+                String::new(),
+            );
+
+            for (send, return_node) in connection.sends.iter().zip(&returns) {
+                if let (true, Some(decibels)) = (send.active, send.decibels()) {
+                    add(
+                        Some(node),
+                        Some(*return_node),
+                        EdgeKind::Send,
+                        format!("{:.1} dB", decibels),
+                    );
+                }
+            }
+            for sidechain in connection.sidechains {
+                add(
+                    node_of(sidechain.input.endpoint(), connection.group_id),
+                    Some(node),
+                    EdgeKind::Sidechain,
+                    sidechain.device.clone(),
+                );
+            }
+        }
+
+        // A track sending midi to another is also listed as the other's input
+        let mut edges: Vec<Edge> = Vec::new();
+        for edge in graph.edges {
+            if !edges
+                .iter()
+                .any(|e| (e.from, e.to, e.kind) == (edge.from, edge.to, edge.kind))
+            {
+                edges.push(edge);
+            }
+        }
+        graph.edges = edges;
+        graph
+    }
+
+    /// The nodes in a column in the order they are drawn from top to bottom
+    pub fn column(&self, column: usize) -> impl Iterator<Item = (usize, &GraphNode)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(move |(_, node)| node.column == column)
+    }
+}
@@ -0,0 +1,248 @@
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::colors;
+use crate::edit::{self, Edit, EditableSet, TrackRef};
+use crate::palsa;
+use crate::parallel;
+use crate::parse::{
+    als::AlsData,
+    track::{Track, TrackKind},
+};
+
+/// Renaming and recolouring rules for tracks, read from a *yaml* file such as
+/// ```yaml
+/// rules:
+///   - pattern: "(?i)^kick.*"
+///     name: KICK
+///     color: 14
+///   - pattern: "(?i)bass"
+///     color: 22
+/// ```
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    rules: Vec<RuleSpec>,
+}
+
+#[derive(Deserialize)]
+struct RuleSpec {
+    pattern: String,
+    name: Option<String>,
+    color: Option<usize>,
+}
+
+struct Rule {
+    /// Matched against track names, only the first rule matching a track is used
+    pattern: Regex,
+    /// Replaces the matched part of the name, `$1` and the like insert capture groups
+    name: Option<String>,
+    /// Index into live's colour palette
+    color: Option<usize>,
+}
+
+/// A change a rule makes to one track of a set
+#[derive(Debug, Clone)]
+pub enum Change {
+    Rename {
+        track: TrackRef,
+        from: String,
+        to: String,
+    },
+    Recolour {
+        track: TrackRef,
+        name: String,
+        from: Option<usize>,
+        to: usize,
+    },
+}
+
+/// The changes rules make to a single set
+pub struct SetPlan {
+    pub path: PathBuf,
+    pub changes: Vec<Change>,
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> Result<Rules, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file: RulesFile = serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Invalid rules in {}: {}", path.display(), e))?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .map(|spec| {
+                if let Some(color) = spec.color {
+                    colors::check_index(color)
+                        .map_err(|e| format!("{} in the rule for \"{}\"", e, spec.pattern))?;
+                }
+                Ok(Rule {
+                    pattern: Regex::new(&spec.pattern)
+                        .map_err(|e| format!("Invalid pattern \"{}\": {}", spec.pattern, e))?,
+                    name: spec.name,
+                    color: spec.color,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Rules { rules })
+    }
+
+    /// The changes which would bring a set's tracks in line with the rules
+    pub fn plan(&self, als_data: &AlsData) -> Vec<Change> {
+        als_data
+            .tracks
+            .iter()
+            .filter(|track| !matches!(track.kind, TrackKind::Master | TrackKind::PreHear))
+            .flat_map(|track| self.plan_track(track))
+            .collect()
+    }
+
+    fn plan_track(&self, track: &Track) -> Vec<Change> {
+        let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.pattern.is_match(&track.name))
+        else {
+            return Vec::new();
+        };
+        // Ids are unique within a set while names need not be
+        let track_ref = || match track.id {
+            -1 => TrackRef::Name(track.name.clone()),
+            id => TrackRef::Id(id),
+        };
+
+        let mut changes = Vec::new();
+        let name = match &rule.name {
+            Some(name) => rule.pattern.replace(&track.name, name).to_string(),
+            None => track.name.clone(),
+        };
+        if name != track.name {
+            changes.push(Change::Rename {
+                track: track_ref(),
+                from: track.name.clone(),
+                to: name.clone(),
+            });
+        }
+        if let Some(color) = rule.color.filter(|&color| track.color != Some(color)) {
+            changes.push(Change::Recolour {
+                track: track_ref(),
+                name,
+                from: track.color,
+                to: color,
+            });
+        }
+        changes
+    }
+}
+
+impl Change {
+    pub fn edit(&self) -> Edit {
+        match self {
+            Change::Rename { track, to, .. } => Edit::RenameTrack {
+                track: track.clone(),
+                name: to.clone(),
+            },
+            Change::Recolour { track, to, .. } => Edit::RecolourTrack {
+                track: track.clone(),
+                color: *to,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Rename { from, to, .. } => {
+                write!(f, "Rename track \"{}\" to \"{}\"", from, to)
+            }
+            Change::Recolour { name, from, to, .. } => match from {
+                Some(from) => write!(f, "Recolour track \"{}\" from {} to {}", name, from, to),
+                None => write!(f, "Colour track \"{}\" {}", name, to),
+            },
+        }
+    }
+}
+
+impl SetPlan {
+    /// Writes a copy of the set with the changes made to it next to the original
+    pub fn apply(&self) -> Result<PathBuf, String> {
+        let output = edit::edited_path(&self.path);
+        let edits: Vec<Edit> = self.changes.iter().map(Change::edit).collect();
+
+        let mut set = EditableSet::open(&self.path)?;
+        set.apply(&edits)
+            .and_then(|_| set.save(&output))
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        Ok(output)
+    }
+}
+
+/// Plans the changes to every set in parallel, leaving out sets which the rules do not change
+pub fn plan_sets(rules: &Rules, files: &[PathBuf]) -> Vec<Result<SetPlan, String>> {
+    files
+        .par_iter()
+        .filter_map(|path| {
+            let changes = match palsa::parse_als_file(path) {
+                // The changes to the part which was salvaged could never be saved
+                Ok(als_data) if als_data.salvaged => {
+                    return Some(Err(format!(
+                        "{}: The file is damaged or truncated",
+                        path.display()
+                    )))
+                }
+                Ok(als_data) => rules.plan(&als_data),
+                Err(e) => return Some(Err(e)),
+            };
+            (!changes.is_empty()).then(|| {
+                Ok(SetPlan {
+                    path: path.clone(),
+                    changes,
+                })
+            })
+        })
+        .collect()
+}
+
+/// The sets of a single *als* file, a project directory or a directory of projects.
+/// Backups and the edited copies written by earlier runs are left out
+pub fn find_sets(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+
+    let mut dirs = vec![path.to_path_buf()];
+    for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
+        let entry_path = entry.map_err(|e| e.to_string())?.path();
+        if entry_path.is_dir() && entry_path.file_name().is_some_and(|name| name != "Backup") {
+            dirs.push(entry_path);
+        }
+    }
+
+    let mut sets = Vec::new();
+    for dir in dirs {
+        let files = parallel::find_projects(&dir.to_string_lossy()).map_err(|e| e.to_string())?;
+        sets.extend(
+            files
+                .into_iter()
+                .map(PathBuf::from)
+                .filter(|file| !edit::is_edited_copy(file)),
+        );
+    }
+    sets.sort();
+    Ok(sets)
+}
@@ -0,0 +1,247 @@
+use rayon::prelude::*;
+use roxmltree::{Document, Node};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::extract;
+use crate::parallel;
+
+/// Where live looks for a referenced file, stored in a `FileRef`'s `RelativePathType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// Only the absolute `Path` is known
+    Absolute,
+    /// Relative to the directory of the set
+    External,
+    /// Relative to the project directory
+    Project,
+    /// Within live's core library, which every installation has
+    CoreLibrary,
+    /// Within the user library or an installed pack
+    Library,
+}
+
+/// A sample file referenced by a set, by a clip or an instrument such as simpler
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SampleReference {
+    /// Empty when only the relative path is known
+    pub path: String,
+    pub relative_path: String,
+    pub location: Location,
+    /// Stored the way live 10 and earlier do, as lists of directories along with a file name
+    pub legacy: bool,
+}
+
+impl Location {
+    fn from_value(value: &str) -> Location {
+        match value {
+            "1" => Location::External,
+            "3" => Location::Project,
+            "5" => Location::CoreLibrary,
+            "6" | "7" => Location::Library,
+            _ => Location::Absolute,
+        }
+    }
+}
+
+impl SampleReference {
+    /// The absolute path, or the relative one when that is all there is
+    pub fn shown_path(&self) -> &str {
+        if self.path.is_empty() {
+            &self.relative_path
+        } else {
+            &self.path
+        }
+    }
+
+    /// Finds where the sample is on disk, trying the absolute path first
+    /// and then the relative one like live does for sets which were moved
+    pub fn resolve(&self, set_dir: &Path, project_dir: &Path) -> Option<PathBuf> {
+        self.candidates(set_dir, project_dir)
+            .into_iter()
+            .find(|path| path.is_file())
+    }
+
+    /// Every place live may look for the sample, whether a file is there or not
+    fn candidates(&self, set_dir: &Path, project_dir: &Path) -> Vec<PathBuf> {
+        let relative = match self.location {
+            Location::External => Some(set_dir.join(&self.relative_path)),
+            Location::Project => Some(project_dir.join(&self.relative_path)),
+            _ => None,
+        };
+
+        [Some(PathBuf::from(&self.path)), relative]
+            .into_iter()
+            .flatten()
+            .filter(|path| !path.as_os_str().is_empty())
+            .collect()
+    }
+}
+
+/// Every distinct sample a set's *xml* references. This needs the full *xml*
+/// as samples of instruments in racks are within elements pruning leaves out.
+/// Fails when a reference can not be read, as which samples the set uses is then unknown
+pub fn references(xml: &str) -> Result<Vec<SampleReference>, String> {
+    let doc = Document::parse(xml).map_err(|e| e.to_string())?;
+
+    let mut references = Vec::new();
+    for file_ref in doc
+        .descendants()
+        .filter(|n| n.has_tag_name("SampleRef"))
+        .filter_map(|n| n.children().find(|n| n.has_tag_name("FileRef")))
+    {
+        let child = |tag_name| {
+            file_ref
+                .children()
+                .find(|n: &Node| n.has_tag_name(tag_name))
+        };
+        let value = |tag_name| child(tag_name).and_then(|n| n.attribute("Value"));
+        let location = Location::from_value(value("RelativePathType").unwrap_or_default());
+
+        let reference = match (value("Path"), value("Name")) {
+            (Some(path), _) => SampleReference {
+                path: path.to_string(),
+                relative_path: value("RelativePath").unwrap_or_default().to_string(),
+                location,
+                legacy: false,
+            },
+            // Devices without a sample loaded have a reference to nothing
+            (None, Some("")) => continue,
+            (None, Some(name)) => SampleReference {
+                path: child("SearchHint")
+                    .and_then(|n| n.children().find(|n| n.has_tag_name("PathHint")))
+                    .filter(|hint| {
+                        hint.children()
+                            .any(|n| n.has_tag_name("RelativePathElement"))
+                    })
+                    .map(|hint| format!("/{}", legacy_path(hint, name)))
+                    .unwrap_or_default(),
+                relative_path: child("RelativePath")
+                    .map(|n| legacy_path(n, name))
+                    .unwrap_or_default(),
+                location,
+                legacy: true,
+            },
+            (None, None) => {
+                return Err(format!(
+                    "Contains a sample reference palsa can not read at byte {}",
+                    file_ref.range().start
+                ))
+            }
+        };
+        if !reference.shown_path().is_empty() {
+            references.push(reference);
+        }
+    }
+
+    references.sort_by(|a, b| (&a.path, &a.relative_path).cmp(&(&b.path, &b.relative_path)));
+    references.dedup();
+    Ok(references)
+}
+
+/// Joins the `RelativePathElement` directories within `node` and a file name
+fn legacy_path(node: Node, name: &str) -> String {
+    node.children()
+        .filter(|n| n.has_tag_name("RelativePathElement"))
+        .filter_map(|n| n.attribute("Dir"))
+        .chain([name])
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A file in a project's `Samples` directory which none of its sets use
+#[derive(Debug, Clone)]
+pub struct UnusedFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Files in the project's `Samples` directory which no set of the project uses, including
+/// its backups. Live's `.asd` analysis files count as used when their sample is, as do
+/// files named like a sample which is missing where a set expects it
+pub fn unused_files(project_dir: &Path) -> Result<Vec<UnusedFile>, String> {
+    let find_sets = |dir: &Path| -> Result<Vec<String>, String> {
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        parallel::find_projects(&dir.to_string_lossy()).map_err(|e| e.to_string())
+    };
+    let mut sets = find_sets(project_dir)?;
+    sets.extend(find_sets(&project_dir.join("Backup"))?);
+
+    let references: Vec<(PathBuf, SampleReference)> = sets
+        .par_iter()
+        .map(|set| {
+            // Salvaging a damaged set would leave out the samples of what could not be read
+            let xml = extract::extract_strict(Path::new(set))
+                .map_err(|e| format!("Failed to extract {}: {}", set, e))?;
+            let set_dir = Path::new(set).parent().unwrap_or(project_dir).to_path_buf();
+
+            Ok(references(&xml)
+                .map_err(|e| format!("{}: {}", set, e))?
+                .into_iter()
+                .map(|reference| (set_dir.clone(), reference))
+                .collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut used: HashSet<PathBuf> = HashSet::new();
+    // Live finds missing samples again by their name, so files of those names are kept too
+    let mut missing_names: HashSet<OsString> = HashSet::new();
+    for (set_dir, reference) in &references {
+        let mut found = false;
+        for path in reference.candidates(set_dir, project_dir) {
+            if let Ok(path) = path.canonicalize() {
+                used.insert(path);
+                found = true;
+            }
+        }
+        if !found {
+            missing_names.extend(
+                Path::new(reference.shown_path())
+                    .file_name()
+                    .map(OsString::from),
+            );
+        }
+    }
+
+    let mut unused = Vec::new();
+    let mut dirs = vec![project_dir.join("Samples")];
+    while let Some(dir) = dirs.pop() {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+
+            let sample = match path.extension() {
+                Some(extension) if extension.eq_ignore_ascii_case("asd") => path.with_extension(""),
+                _ => path.clone(),
+            };
+            let is_used = sample
+                .canonicalize()
+                .is_ok_and(|sample| used.contains(&sample))
+                || sample
+                    .file_name()
+                    .is_some_and(|name| missing_names.contains(name));
+            if !is_used {
+                let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+                unused.push(UnusedFile { path, size });
+            }
+        }
+    }
+
+    unused.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(unused)
+}
@@ -0,0 +1,150 @@
+use crate::parse::als::AlsData;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What the als file list is narrowed down to, a set has to pass every filter which is set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Search {
+    /// Fuzzy matched against set names
+    pub query: String,
+    /// Inclusive range in beats per minute
+    pub tempo: Option<(f32, f32)>,
+    /// Inclusive range of the number of tracks
+    pub track_count: Option<(usize, usize)>,
+    /// Text which a device, sample or track name of the set has to contain
+    pub contains: String,
+    pub contains_kind: ContainsKind,
+    pub modified_within_days: Option<u32>,
+}
+
+/// What `Search::contains` is looked for in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ContainsKind {
+    #[default]
+    Device,
+    Sample,
+    Track,
+}
+
+impl ContainsKind {
+    pub const ALL: [ContainsKind; 3] = [
+        ContainsKind::Device,
+        ContainsKind::Sample,
+        ContainsKind::Track,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContainsKind::Device => "Device or plugin",
+            ContainsKind::Sample => "Sample",
+            ContainsKind::Track => "Track",
+        }
+    }
+}
+
+impl Search {
+    /// Whether any part of the search narrows down the list
+    pub fn is_active(&self) -> bool {
+        !self.query.trim().is_empty()
+            || self.tempo.is_some()
+            || self.track_count.is_some()
+            || !self.contains.trim().is_empty()
+            || self.modified_within_days.is_some()
+    }
+
+    /// Whether a set passes the search, returning which characters of its name
+    /// were matched by the query so they can be highlighted
+    pub fn matches(&self, als_data: &AlsData, now: u64) -> Option<Vec<usize>> {
+        if let Some((min, max)) = self.tempo {
+            if als_data.tempo < min || als_data.tempo > max {
+                return None;
+            }
+        }
+
+        if let Some((min, max)) = self.track_count {
+            if als_data.track_count() < min || als_data.track_count() > max {
+                return None;
+            }
+        }
+
+        if let Some(days) = self.modified_within_days {
+            if now.saturating_sub(als_data.modified) > days as u64 * 24 * 60 * 60 {
+                return None;
+            }
+        }
+
+        let contains = self.contains.trim().to_lowercase();
+        if !contains.is_empty() {
+            let found = |name: &str| name.to_lowercase().contains(&contains);
+            let tracks = als_data.tracks.iter();
+            let is_found = match self.contains_kind {
+                ContainsKind::Device => tracks
+                    .flat_map(|track| &track.devices)
+                    .any(|device| found(&device.name)),
+                ContainsKind::Sample => tracks
+                    .flat_map(|track| &track.clips)
+                    .filter_map(|clip| clip.source.as_deref())
+                    .any(found),
+                ContainsKind::Track => tracks.map(|track| &track.name).any(|name| found(name)),
+            };
+            if !is_found {
+                return None;
+            }
+        }
+
+        fuzzy_match(&self.query, &als_data.name)
+    }
+}
+
+/// Matches when every character of `query`, ignoring case and whitespace, appears in `text`
+/// in order, returning the indices of the matched characters of `text`.
+/// A query found as a whole is preferred so its characters are highlighted together
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Vec<usize>> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    // Lowercasing some characters produces several so each is kept with its original index
+    let text: Vec<(usize, char)> = text
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| c.to_lowercase().map(move |c| (i, c)))
+        .collect();
+
+    if query.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let chars: Vec<char> = text.iter().map(|&(_, c)| c).collect();
+    if let Some(start) = chars
+        .windows(query.len())
+        .position(|window| window == query.as_slice())
+    {
+        let mut indices: Vec<usize> = text[start..start + query.len()]
+            .iter()
+            .map(|&(i, _)| i)
+            .collect();
+        indices.dedup();
+        return Some(indices);
+    }
+
+    let mut indices = Vec::new();
+    let mut query_chars = query.iter().peekable();
+    for &(i, c) in &text {
+        if query_chars.peek() == Some(&&c) {
+            query_chars.next();
+            if indices.last() != Some(&i) {
+                indices.push(i);
+            }
+        }
+    }
+
+    query_chars.peek().is_none().then_some(indices)
+}
+
+/// Seconds since the unix epoch, to compare with `AlsData::modified`
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
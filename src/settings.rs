@@ -0,0 +1,54 @@
+use crate::sort::Sort;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const SETTINGS_PATH: &str = "settings.yaml";
+
+/// Preferences which are kept between runs of palsa in `settings.yaml`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Settings {
+    /// How the als file list is sorted
+    #[serde(default)]
+    pub sort: Sort,
+    /// Sets which decompress to more megabytes of xml than this are not loaded
+    #[serde(default = "default_max_decompressed_mb")]
+    pub max_decompressed_mb: u64,
+    /// Sets not saved in this many months are listed in the statistics tab
+    #[serde(default = "default_stale_months")]
+    pub stale_months: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sort: Sort::default(),
+            max_decompressed_mb: default_max_decompressed_mb(),
+            stale_months: default_stale_months(),
+        }
+    }
+}
+
+/// Even the largest sets seen so far decompress to less than a gigabyte of xml
+fn default_max_decompressed_mb() -> u64 {
+    2000
+}
+
+fn default_stale_months() -> u32 {
+    6
+}
+
+impl Settings {
+    /// Loads the settings, falling back to the defaults if there are none yet or they are invalid
+    pub fn load() -> Settings {
+        fs::read_to_string(SETTINGS_PATH)
+            .ok()
+            .and_then(|contents| serde_yaml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let yaml = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(SETTINGS_PATH, yaml)
+            .map_err(|e| format!("Failed to save settings to {}: {}", SETTINGS_PATH, e))
+    }
+}
@@ -0,0 +1,110 @@
+use crate::parse::als::{AlsData, Project};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Columns of the als file list which it can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub enum SortColumn {
+    #[default]
+    Name,
+    Project,
+    Modified,
+    Tempo,
+    Length,
+    Tracks,
+    Clips,
+    FileSize,
+    Version,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 9] = [
+        SortColumn::Name,
+        SortColumn::Project,
+        SortColumn::Modified,
+        SortColumn::Tempo,
+        SortColumn::Length,
+        SortColumn::Tracks,
+        SortColumn::Clips,
+        SortColumn::FileSize,
+        SortColumn::Version,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Name => "Name",
+            SortColumn::Project => "Project",
+            SortColumn::Modified => "Last modified",
+            SortColumn::Tempo => "Tempo",
+            SortColumn::Length => "Bars",
+            SortColumn::Tracks => "Tracks",
+            SortColumn::Clips => "Clips",
+            SortColumn::FileSize => "Size",
+            SortColumn::Version => "Live",
+        }
+    }
+
+    fn compare(&self, a: (&Project, &AlsData), b: (&Project, &AlsData)) -> Ordering {
+        let clips = |als_data: &AlsData| -> usize {
+            als_data.tracks.iter().map(|track| track.clips.len()).sum()
+        };
+
+        match self {
+            SortColumn::Name => compare_names(&a.1.name, &b.1.name),
+            SortColumn::Project => compare_names(&a.0.name, &b.0.name),
+            SortColumn::Modified => a.1.modified.cmp(&b.1.modified),
+            SortColumn::Tempo => a.1.tempo.total_cmp(&b.1.tempo),
+            SortColumn::Length => a.1.length().total_cmp(&b.1.length()),
+            SortColumn::Tracks => a.1.track_count().cmp(&b.1.track_count()),
+            SortColumn::Clips => clips(a.1).cmp(&clips(b.1)),
+            SortColumn::FileSize => a.1.file_size.cmp(&b.1.file_size),
+            SortColumn::Version => a.1.version.compare(&b.1.version),
+        }
+    }
+}
+
+/// How the als file list is sorted, kept between runs in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Sort {
+    pub column: SortColumn,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// Sorts `(project, set)` indices into `projects`,
+    /// sets which are equal in the sorted column are ordered by project then name
+    pub fn sort(&self, projects: &[Project], sets: &mut [(usize, usize)]) {
+        let get = |(project_index, als_index): (usize, usize)| {
+            let project = &projects[project_index];
+            (project, &project.als_data.as_ref().unwrap()[als_index])
+        };
+
+        sets.sort_by(|&a, &b| {
+            let (a, b) = (get(a), get(b));
+            let ordering = self.column.compare(a, b);
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            ordering
+                .then_with(|| SortColumn::Project.compare(a, b))
+                .then_with(|| SortColumn::Name.compare(a, b))
+        });
+    }
+
+    /// Sorts by `column`, flipping the direction when it is already sorted by it.
+    /// Other than names columns start out descending so the newest and biggest sets come first
+    pub fn toggle(&mut self, column: SortColumn) {
+        if self.column == column {
+            self.descending = !self.descending;
+        } else {
+            self.column = column;
+            self.descending = !matches!(column, SortColumn::Name | SortColumn::Project);
+        }
+    }
+}
+
+fn compare_names(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
@@ -0,0 +1,166 @@
+use std::{cmp::Reverse, collections::HashMap, fs, path::Path};
+
+use crate::parse::als::{self, Project};
+
+/// Width of each range of tempos the sets are counted in
+pub const TEMPO_BUCKET: f32 = 10.;
+
+/// An overview of the whole library, shown in the statistics tab
+#[derive(Debug, Clone, Default)]
+pub struct LibraryStats {
+    pub projects: Vec<ProjectStats>,
+    pub sets: usize,
+    pub tracks: usize,
+    pub clips: usize,
+    /// Names of plugins directly on tracks along with how many sets use them, most used first
+    pub plugins: Vec<(String, usize)>,
+    /// The lowest tempo of each range of `TEMPO_BUCKET` bpm along with how many sets are in it,
+    /// sets with a tempo live can not be set to are left out
+    pub tempos: Vec<(f32, usize)>,
+    pub average_bars: f32,
+    pub average_seconds: f32,
+    /// Project and set names along with when the set was last saved, least recently first
+    pub sets_by_modified: Vec<(String, String, u64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectStats {
+    pub name: String,
+    pub sets: usize,
+    pub backups: usize,
+    pub tracks: usize,
+    pub clips: usize,
+    /// Size of everything in the project directory in bytes, including samples and backups
+    pub disk_usage: u64,
+}
+
+impl LibraryStats {
+    /// Sums up the loaded sets of the projects, backups only count towards disk usage.
+    /// Walks every project directory to measure it so this is best done off the gui thread
+    pub fn new(projects: &[Project], projects_dir: &Path) -> LibraryStats {
+        let mut stats = LibraryStats::default();
+        let mut plugin_sets: HashMap<String, usize> = HashMap::new();
+        let mut tempos: HashMap<i32, usize> = HashMap::new();
+        let mut total_bars = 0.;
+        let mut total_seconds = 0.;
+        let mut timed_sets = 0;
+
+        for project in projects {
+            let sets = project.als_data.as_deref().unwrap_or_default();
+            let mut project_stats = ProjectStats {
+                name: project.name.clone(),
+                sets: sets.len(),
+                backups: project.backups.len(),
+                tracks: 0,
+                clips: 0,
+                disk_usage: dir_size(&projects_dir.join(&project.name)),
+            };
+
+            for als_data in sets {
+                project_stats.tracks += als_data.track_count();
+                project_stats.clips += als_data
+                    .tracks
+                    .iter()
+                    .map(|track| track.clips.len())
+                    .sum::<usize>();
+
+                let mut plugins: Vec<&str> = als_data
+                    .tracks
+                    .iter()
+                    .flat_map(|track| &track.devices)
+                    .filter(|device| device.kind.ends_with("PluginDevice"))
+                    .map(|device| device.name.as_str())
+                    .collect();
+                plugins.sort_unstable();
+                plugins.dedup();
+                for plugin in plugins {
+                    *plugin_sets.entry(plugin.to_string()).or_default() += 1;
+                }
+
+                total_bars += als_data.length_in_bars() as f32;
+                // A damaged tempo would stretch the histogram over millions of empty ranges
+                if als::TEMPO_RANGE.contains(&als_data.tempo) {
+                    *tempos
+                        .entry((als_data.tempo / TEMPO_BUCKET).floor() as i32)
+                        .or_default() += 1;
+                    total_seconds += als_data.length() * 60. / als_data.tempo;
+                    timed_sets += 1;
+                }
+
+                stats.sets_by_modified.push((
+                    project.name.clone(),
+                    als_data.name.clone(),
+                    als_data.modified,
+                ));
+            }
+
+            stats.sets += project_stats.sets;
+            stats.tracks += project_stats.tracks;
+            stats.clips += project_stats.clips;
+            stats.projects.push(project_stats);
+        }
+
+        if stats.sets > 0 {
+            stats.average_bars = total_bars / stats.sets as f32;
+        }
+        if timed_sets > 0 {
+            stats.average_seconds = total_seconds / timed_sets as f32;
+        }
+
+        stats.plugins = plugin_sets.into_iter().collect();
+        stats
+            .plugins
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        // Every range between the slowest and fastest set is listed, including empty ones
+        if let (Some(&lowest), Some(&highest)) = (tempos.keys().min(), tempos.keys().max()) {
+            stats.tempos = (lowest..=highest)
+                .map(|bucket| {
+                    (
+                        bucket as f32 * TEMPO_BUCKET,
+                        tempos.get(&bucket).copied().unwrap_or(0),
+                    )
+                })
+                .collect();
+        }
+
+        stats
+            .projects
+            .sort_by_key(|project| Reverse(project.disk_usage));
+        stats
+            .sets_by_modified
+            .sort_by_key(|(_, _, modified)| *modified);
+        stats
+    }
+
+    pub fn disk_usage(&self) -> u64 {
+        self.projects.iter().map(|project| project.disk_usage).sum()
+    }
+
+    /// Sets which were last saved before `before`, in seconds since the unix epoch
+    pub fn untouched_since(&self, before: u64) -> impl Iterator<Item = &(String, String, u64)> {
+        self.sets_by_modified
+            .iter()
+            .take_while(move |(_, _, modified)| *modified < before)
+    }
+}
+
+/// The size of every file within a directory in bytes, unreadable files are left out
+fn dir_size(dir: &Path) -> u64 {
+    let mut size = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => dirs.push(entry.path()),
+                Ok(metadata) => size += metadata.len(),
+                Err(_) => {}
+            }
+        }
+    }
+    size
+}
@@ -0,0 +1,121 @@
+#[cfg(unix)]
+use std::{env, fs, path::PathBuf};
+use std::{io, path::Path};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::{history::BackupTimestamp, search};
+
+/// Moves a file to the trash of the user's desktop so it can still be restored,
+/// following the freedesktop.org trash specification on linux
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn move_to_trash(path: &Path) -> io::Result<()> {
+    let trash_dir = match env::var_os("XDG_DATA_HOME") {
+        Some(data_home) => PathBuf::from(data_home).join("Trash"),
+        None => home_dir()?.join(".local/share/Trash"),
+    };
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let destination = unused_name(&files_dir, path)?;
+    let name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    // The specification asks for the deletion date in local time
+    let time = BackupTimestamp::from_unix_local(search::now());
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={:04}-{:02}-{:02}T{:02}:{:02}:{:02}\n",
+        percent_encode(&path.canonicalize()?.to_string_lossy()),
+        time.year,
+        time.month,
+        time.day,
+        time.hour,
+        time.minute,
+        time.second
+    );
+    // The info file is written first as the specification asks, it also reserves the name
+    let info_path = info_dir.join(format!("{}.trashinfo", name));
+    fs::write(&info_path, info)?;
+
+    move_file(path, &destination).inspect_err(|_| {
+        let _ = fs::remove_file(&info_path);
+    })
+}
+
+/// Moves a file to the trash of the user's desktop so it can still be restored
+#[cfg(target_os = "macos")]
+pub fn move_to_trash(path: &Path) -> io::Result<()> {
+    let trash_dir = home_dir()?.join(".Trash");
+    let destination = unused_name(&trash_dir, path)?;
+    move_file(path, &destination)
+}
+
+#[cfg(not(unix))]
+pub fn move_to_trash(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Moving files to the recycle bin is not supported on this platform yet",
+    ))
+}
+
+#[cfg(unix)]
+fn home_dir() -> io::Result<PathBuf> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+/// A path in `dir` for the file which nothing else has, numbering it if needed
+#[cfg(unix)]
+fn unused_name(dir: &Path, path: &Path) -> io::Result<PathBuf> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|number| match number {
+            1 => dir.join(format!("{}{}", stem, extension)),
+            number => dir.join(format!("{} {}{}", stem, number, extension)),
+        })
+        .find(|destination| {
+            // Names are also taken by the info files of the linux trash
+            let name = destination
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let info = dir
+                .with_file_name("info")
+                .join(format!("{}.trashinfo", name));
+            !destination.exists() && !info.exists()
+        })
+        .ok_or_else(|| io::Error::other("No free name in the trash"))
+}
+
+/// Renames the file, copying it instead when the trash is on another drive
+#[cfg(unix)]
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// Encodes a path for a `.trashinfo` file, which stores them like urls
+#[cfg(all(unix, not(target_os = "macos")))]
+fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, mpsc},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::cache;
+use crate::message::Message;
+use crate::parallel;
+use crate::parse::als::Project;
+use crate::progress::Progress;
+
+/// How often the project directories are checked for changed sets
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Modification times of every *als* file of the projects and their backups
+pub type Snapshot = HashMap<PathBuf, SystemTime>;
+
+/// Lists every *als* file in the same layout `palsa::run_palsa` loads
+pub fn snapshot(dir: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+
+    let entries = |dir: &Path| {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+    };
+
+    for project_path in entries(dir).filter(|path| path.is_dir()) {
+        for als_path in entries(&project_path).chain(entries(&project_path.join("Backup"))) {
+            if als_path.extension().unwrap_or_default() == "als" {
+                if let Ok(modified) = fs::metadata(&als_path).and_then(|m| m.modified()) {
+                    snapshot.insert(als_path, modified);
+                }
+            }
+        }
+    }
+    snapshot
+}
+
+/// Polls `dir` for new, modified, renamed and deleted *als* files compared to `known`,
+/// parsing changed files again, updating the cache and sending the changes to the gui.
+/// A file is only handled once it has not changed for one poll so sets which live is still
+/// saving are not read. Returns once the gui has closed
+pub fn watch(dir: &Path, mut known: Snapshot, sender: mpsc::Sender<Message>) {
+    let mut previous = known.clone();
+
+    let progress_sender = sender.clone();
+    let progress = Progress::new(move |report| {
+        let _ = progress_sender.send(Message::Progress(report.clone()));
+    });
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let current = snapshot(dir);
+
+        // Changed and removed files grouped by the cache directory they belong in
+        let mut changed: BTreeMap<(String, bool), Vec<String>> = BTreeMap::new();
+        let mut removed: Vec<(String, bool, String)> = Vec::new();
+
+        for (path, modified) in &current {
+            if known.get(path) != Some(modified) && previous.get(path) == Some(modified) {
+                if let Some((project, backup, _)) = locate(dir, path) {
+                    changed
+                        .entry((project, backup))
+                        .or_default()
+                        .push(path.to_string_lossy().to_string());
+                }
+                known.insert(path.clone(), *modified);
+            }
+        }
+        known.retain(|path, _| {
+            let is_removed = !current.contains_key(path) && !previous.contains_key(path);
+            if is_removed {
+                removed.extend(locate(dir, path));
+            }
+            !is_removed
+        });
+        previous = current;
+
+        for (project, backup, name) in removed {
+            let cache_dir = cache_dir(&project, backup);
+            if let Err(e) = cache::remove(&cache_dir, &name) {
+                eprintln!("Error removing cache of {}: {:?}", name, e);
+            }
+            let message = Message::SetRemoved {
+                project,
+                backup,
+                name,
+            };
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+
+        if !changed.is_empty() {
+            progress.discovered(changed.values().map(Vec::len).sum());
+        }
+        for ((project, backup), files) in changed {
+            let messages = match parallel::parallel_parse_files(
+                &cache_dir(&project, backup),
+                &files,
+                &progress,
+                &AtomicBool::new(false),
+            ) {
+                Ok(all_als_data) => {
+                    let (als_data, backups) = if backup {
+                        (None, all_als_data.clone())
+                    } else {
+                        (Some(all_als_data.clone()), Vec::new())
+                    };
+                    if let Err(e) = cache::cache(vec![Project {
+                        name: project.clone(),
+                        als_data,
+                        backups,
+                    }]) {
+                        eprintln!("Error creating cache: {:?}", e);
+                    }
+
+                    all_als_data
+                        .into_iter()
+                        .map(|als_data| Message::SetChanged {
+                            project: project.clone(),
+                            backup,
+                            als_data,
+                        })
+                        .collect()
+                }
+                Err(error) => vec![Message::Error(error)],
+            };
+
+            for message in messages {
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The project, whether it is a backup and the set name of an *als* file within `dir`
+fn locate(dir: &Path, path: &Path) -> Option<(String, bool, String)> {
+    let name = path.file_stem()?.to_str()?.to_string();
+    let parent = path.parent()?;
+    let backup = parent.file_name()? == "Backup" && parent.parent()? != dir;
+    let project_path = if backup { parent.parent()? } else { parent };
+    let project = project_path.file_name()?.to_str()?.to_string();
+
+    Some((project, backup, name))
+}
+
+fn cache_dir(project: &str, backup: bool) -> String {
+    if backup {
+        format!("cache/{}/Backup", project)
+    } else {
+        format!("cache/{}", project)
+    }
+}